@@ -1,12 +1,28 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::{Arc, Mutex};
 
 use eframe::epaint::ColorImage;
 use futures::Future;
 
 use crate::assets;
-use crate::widgets::{self, TileTableCommand};
-use eframe::egui::{self, Context, Ui};
+use crate::export::{self, RoomImageOptions};
+use crate::formats::graphics as graphics_format;
+use crate::formats::palette::{self as palette_format, PaletteFormat};
+use crate::formats::patch::{self, PatchFormat};
+use crate::history::{
+    self, Affected, History, LevelReplacement, Operation, PixelEdit, ScrollsReplacement,
+};
+use crate::notifications;
+#[cfg(feature = "persistence")]
+use crate::project::{self, Project};
+use crate::recent_roms::RecentRoms;
+use crate::rom;
+use crate::room::{self, scroll, PlmKind, RoomData, RoomHeader};
+use crate::widgets::{
+    self, DoorListCommand, EnemyCommand, GraphicsCommand, PaletteFileCommand, RoomBrowserCommand,
+    TileEntry, TileTableCommand,
+};
+use eframe::egui::{self, Context, Key, KeyboardShortcut, Modifiers, Ui};
 
 use zen::graphics::IndexedColor;
 use zen::super_metroid::{
@@ -16,8 +32,10 @@ use zen::super_metroid::{
 };
 
 lazy_static::lazy_static! {
-    static ref SELECTED_FILE_DATA: Mutex<Option<Vec<u8>>> = Mutex::new(std::fs::read("/home/rondao/roms/snes/SuperMetroid.smc").ok());
+    static ref SELECTED_FILE: Mutex<Option<SelectedFile>> = Mutex::new(None);
+    static ref IMPORTED_FILE: Mutex<Option<FileImport>> = Mutex::new(None);
     static ref EDITOR_ASSETS: Arc<Mutex<HashMap<assets::BtsTile, ColorImage>>> = Arc::new(Mutex::new(HashMap::new()));
+    static ref PLM_ICONS: Arc<Mutex<HashMap<PlmKind, ColorImage>>> = Arc::new(Mutex::new(HashMap::new()));
 }
 
 pub struct ZenSM {
@@ -25,25 +43,66 @@ pub struct ZenSM {
     palette_editor: widgets::PaletteEditor,
     graphics_editor: widgets::GraphicsEditor,
     tiletable_editor: widgets::TileTableEditor,
+    block_composer: widgets::BlockComposer,
     level_editor: widgets::LevelEditor,
+    bts_palette: widgets::BtsPalette,
+    room_properties: widgets::RoomProperties,
+    door_list: widgets::DoorList,
+    enemy_editor: widgets::EnemyEditor,
+    plm_editor: widgets::PlmEditor,
+    room_browser: widgets::RoomBrowser,
+    show_room_browser: bool,
     sorted_room_list: Vec<usize>,
     selected_tileset: Option<TilesetSelection>,
     selected_room: Option<RoomSelection>,
+    history: History,
+    room_data: RoomData,
+    room_image_options: RoomImageOptions,
+    notification_log: widgets::NotificationLog,
+    recent_roms: RecentRoms,
+    copier_header: Option<Vec<u8>>, // Header of the loaded ROM file, if it had one.
+    keep_copier_header: bool,       // Write the header back when saving.
+    // The ROM as loaded, that patches and projects are made from.
+    base_rom: Vec<u8>,
+    // Waiting for the user to confirm that unsaved edits can be lost.
+    unsaved_prompt: Option<UnsavedAction>,
+    closing: bool,        // Closing was confirmed, with unsaved edits.
+    title_modified: bool, // Whether the window title shows unsaved edits.
 }
 
 impl Default for ZenSM {
     fn default() -> Self {
         assets::load_bts_icons(Arc::clone(&EDITOR_ASSETS));
+        assets::load_plm_icons(Arc::clone(&PLM_ICONS));
 
         Self {
             sm: SuperMetroid::default(),
             palette_editor: widgets::PaletteEditor::default(),
             graphics_editor: widgets::GraphicsEditor::default(),
             tiletable_editor: widgets::TileTableEditor::default(),
+            block_composer: widgets::BlockComposer::default(),
             level_editor: widgets::LevelEditor::new(Arc::clone(&EDITOR_ASSETS)),
+            bts_palette: widgets::BtsPalette::new(Arc::clone(&EDITOR_ASSETS)),
+            room_properties: widgets::RoomProperties::default(),
+            door_list: widgets::DoorList::default(),
+            enemy_editor: widgets::EnemyEditor::default(),
+            plm_editor: widgets::PlmEditor::new(Arc::clone(&PLM_ICONS)),
+            room_browser: widgets::RoomBrowser::default(),
+            show_room_browser: true,
             sorted_room_list: Vec::default(),
             selected_tileset: Option::default(),
             selected_room: Option::default(),
+            history: History::default(),
+            room_data: RoomData::default(),
+            room_image_options: RoomImageOptions::default(),
+            notification_log: widgets::NotificationLog::default(),
+            recent_roms: RecentRoms::default(),
+            copier_header: None,
+            keep_copier_header: true,
+            base_rom: Vec::new(),
+            unsaved_prompt: None,
+            closing: false,
+            title_modified: false,
         }
     }
 }
@@ -60,29 +119,169 @@ struct TilesetSelection {
     pub data: Tileset,
 }
 
+/// A ROM file picked by the user, waiting to be loaded.
+struct SelectedFile {
+    path: String, // Only the file name, on web.
+    data: Vec<u8>,
+}
+
+/// What a file picked by the user is imported as.
+enum ImportKind {
+    // Palette picked when the dialog opened, and its sub palette replaced, or the whole palette.
+    Palette(usize, Option<usize>),
+    Graphics,
+    Patch,
+    #[cfg(feature = "persistence")]
+    Project,
+}
+
+/// A file picked by the user, waiting to be imported.
+struct FileImport {
+    kind: ImportKind,
+    file_name: String,
+    data: Vec<u8>,
+}
+
+/// An action that loses the unsaved edits.
+enum UnsavedAction {
+    LoadFromFile,
+    OpenRom(String),
+    ApplyPatch,
+    #[cfg(feature = "persistence")]
+    OpenProject,
+    Close,
+}
+
+enum UnsavedChoice {
+    Save,
+    Discard,
+    Cancel,
+}
+
 enum Menu {
     LoadFromFile,
+    OpenRecent(String),
     SaveToFile,
+    ApplyPatch,
+    ExportPatch(PatchFormat),
+    #[cfg(feature = "persistence")]
+    OpenProject,
+    #[cfg(feature = "persistence")]
+    SaveProject,
+    ExportRoomImage,
+    Undo,
+    Redo,
+    ErrorLog,
     None,
 }
 
+const UNDO_SHORTCUT: KeyboardShortcut = KeyboardShortcut::new(Modifiers::COMMAND, Key::Z);
+const REDO_SHORTCUT: KeyboardShortcut =
+    KeyboardShortcut::new(Modifiers::COMMAND.plus(Modifiers::SHIFT), Key::Z);
+
+impl ZenSM {
+    /// Opens `rom_path` right away, when given.
+    #[cfg_attr(not(feature = "persistence"), allow(unused_variables))]
+    pub fn new(cc: &eframe::CreationContext<'_>, rom_path: Option<String>) -> Self {
+        let mut app = Self::default();
+
+        #[cfg(feature = "persistence")]
+        if let Some(storage) = cc.storage {
+            app.recent_roms =
+                eframe::get_value(storage, crate::recent_roms::STORAGE_KEY).unwrap_or_default();
+            app.room_browser.bookmarks =
+                eframe::get_value(storage, widgets::RoomBookmarks::STORAGE_KEY).unwrap_or_default();
+        }
+
+        if let Some(path) = rom_path {
+            app.open_rom(path);
+        }
+        app
+    }
+}
+
 impl eframe::App for ZenSM {
+    #[cfg(feature = "persistence")]
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        eframe::set_value(storage, crate::recent_roms::STORAGE_KEY, &self.recent_roms);
+        let bookmarks = &self.room_browser.bookmarks;
+        eframe::set_value(storage, widgets::RoomBookmarks::STORAGE_KEY, bookmarks);
+    }
+
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         // puffin::profile_function!();
         // puffin::GlobalProfiler::lock().new_frame();
 
         // Check if user selected a file.
-        if let Ok(mut mutex_content) = SELECTED_FILE_DATA.lock() {
-            if let Some(data) = &*mutex_content {
-                self.load_data_rom(data);
+        let selected_file = SELECTED_FILE.lock().unwrap().take();
+        if let Some(file) = selected_file {
+            if self.load_data_rom(&file.data) {
+                // On web, only list the ROM if it can be reopened from local storage.
+                #[cfg(target_arch = "wasm32")]
+                if crate::recent_roms::cache_rom(&file.path, &file.data) {
+                    self.recent_roms.add(file.path);
+                } else {
+                    notifications::warning("The ROM is too large to be reopened from recent ROMs");
+                }
+                #[cfg(not(target_arch = "wasm32"))]
+                self.recent_roms.add(file.path);
                 self.reload_textures(ctx);
-                *mutex_content = None;
             }
         }
+        let import = IMPORTED_FILE.lock().unwrap().take();
+        if let Some(import) = import {
+            match import.kind {
+                ImportKind::Palette(palette_address, sub_palette) => self.import_palette(
+                    ctx,
+                    &import.file_name,
+                    &import.data,
+                    palette_address,
+                    sub_palette,
+                ),
+                ImportKind::Graphics => self.import_graphics(ctx, &import.file_name, &import.data),
+                ImportKind::Patch => self.apply_patch(ctx, &import.file_name, &import.data),
+                #[cfg(feature = "persistence")]
+                ImportKind::Project => self.open_project(ctx, &import.file_name, &import.data),
+            }
+        }
+
+        if ctx.input(|i| i.viewport().close_requested())
+            && !self.closing
+            && !self.history.modified().is_empty()
+        {
+            ctx.send_viewport_cmd(egui::ViewportCommand::CancelClose);
+            self.unsaved_prompt = Some(UnsavedAction::Close);
+        }
+        self.update_title(ctx);
+
+        // Edits are merged only within a single interaction, like a drag or typing in a field.
+        if !ctx.input(|i| i.pointer.any_down()) && !ctx.wants_keyboard_input() {
+            self.history.end_interaction();
+        }
+
+        // Redo is checked first, as its shortcut also contains the undo one.
+        if ctx.input_mut(|i| i.consume_shortcut(&REDO_SHORTCUT)) {
+            self.redo(ctx);
+        } else if ctx.input_mut(|i| i.consume_shortcut(&UNDO_SHORTCUT)) {
+            self.undo(ctx);
+        }
 
-        egui::TopBottomPanel::top("top_menu").show(ctx, |ui| match ZenSM::draw_menu(ui) {
-            Menu::LoadFromFile => self.load_from_file(),
-            Menu::SaveToFile => self.save_to_file(),
+        egui::TopBottomPanel::top("top_menu").show(ctx, |ui| match self.draw_menu(ui) {
+            Menu::LoadFromFile => self.confirm_unsaved(ctx, UnsavedAction::LoadFromFile),
+            Menu::OpenRecent(path) => self.confirm_unsaved(ctx, UnsavedAction::OpenRom(path)),
+            Menu::SaveToFile => {
+                self.save_to_file();
+            }
+            Menu::ApplyPatch => self.confirm_unsaved(ctx, UnsavedAction::ApplyPatch),
+            Menu::ExportPatch(format) => self.export_patch(format),
+            #[cfg(feature = "persistence")]
+            Menu::OpenProject => self.confirm_unsaved(ctx, UnsavedAction::OpenProject),
+            #[cfg(feature = "persistence")]
+            Menu::SaveProject => self.save_project(),
+            Menu::ExportRoomImage => self.export_room_image(),
+            Menu::Undo => self.undo(ctx),
+            Menu::Redo => self.redo(ctx),
+            Menu::ErrorLog => self.notification_log.open(),
             Menu::None => (),
         });
 
@@ -103,56 +302,566 @@ impl eframe::App for ZenSM {
                 egui::TopBottomPanel::top("RoomSelectorPanel").show_inside(ui, |ui| {
                     self.draw_room_selector(ui);
                 });
+                egui::TopBottomPanel::top("RoomPropertiesPanel").show_inside(ui, |ui| {
+                    egui::CollapsingHeader::new("Room properties").show(ui, |ui| {
+                        self.draw_room_properties(ui);
+                    });
+                });
+                egui::TopBottomPanel::top("DoorsPanel").show_inside(ui, |ui| {
+                    egui::CollapsingHeader::new("Doors").show(ui, |ui| {
+                        self.draw_doors(ui);
+                    });
+                });
+                egui::TopBottomPanel::top("EnemiesPanel").show_inside(ui, |ui| {
+                    egui::CollapsingHeader::new("Enemies").show(ui, |ui| {
+                        self.draw_enemies(ui);
+                    });
+                });
+                egui::TopBottomPanel::top("PlmsPanel").show_inside(ui, |ui| {
+                    egui::CollapsingHeader::new("PLMs").show(ui, |ui| {
+                        self.draw_plms(ui);
+                    });
+                });
                 egui::TopBottomPanel::top("TilesetPanel").show_inside(ui, |ui| {
                     self.tileset_selector(ui);
                 });
+                egui::TopBottomPanel::top("BlockComposerPanel").show_inside(ui, |ui| {
+                    egui::CollapsingHeader::new("Block composer").show(ui, |ui| {
+                        self.draw_block_composer(ui);
+                    });
+                });
+                egui::TopBottomPanel::bottom("BtsPalettePanel")
+                    .resizable(true)
+                    .default_height(200.0)
+                    .show_inside(ui, |ui| {
+                        self.draw_bts_palette(ui);
+                    });
                 self.draw_graphics(ui);
             });
 
+        if self.show_room_browser && self.selected_room.is_some() {
+            egui::SidePanel::left("room_browser")
+                .resizable(true)
+                .default_width(200.0)
+                .show(ctx, |ui| {
+                    self.draw_room_browser(ui);
+                });
+        }
+
         egui::CentralPanel::default().show(ctx, |ui| {
-            self.draw_level(ui);
+            if self.selected_room.is_some() {
+                self.draw_level(ui);
+            } else {
+                self.draw_welcome(ui);
+            }
         });
+
+        self.draw_unsaved_prompt(ctx);
+        self.notification_log.ui(ctx);
     }
 }
 
 // File manipulation.
 impl ZenSM {
-    fn load_data_rom(&mut self, data: &Vec<u8>) {
-        if let Ok(sm) = super_metroid::load_unheadered_rom(data.clone()) {
-            self.sm = sm;
+    /// Returns whether the ROM could be loaded.
+    fn load_data_rom(&mut self, data: &Vec<u8>) -> bool {
+        let (copier_header, rom_data) = rom::split_copier_header(data);
+        if !self.load_unheadered_rom(rom_data.clone()) {
+            return false;
+        }
+        self.copier_header = copier_header;
+        self.base_rom = rom_data;
+        true
+    }
 
-            self.sorted_room_list = self.sm.rooms.keys().map(|value| *value).collect();
-            self.sorted_room_list.sort();
+    /// Edit `rom_data`, keeping the base ROM. Returns whether it could be loaded.
+    fn load_unheadered_rom(&mut self, rom_data: Vec<u8>) -> bool {
+        match super_metroid::load_unheadered_rom(rom_data) {
+            Ok(sm) => self.sm = sm,
+            Err(e) => {
+                notifications::error(format!("Failed to load the ROM: {:?}", e));
+                return false;
+            }
+        }
 
-            let room = &self.sm.rooms[&self.sorted_room_list[0]];
-            let state_addr = room.state_conditions[0].state_address as usize;
+        self.room_data = RoomData::load(&self.sm);
+        self.reset_selection();
+        true
+    }
 
-            self.selected_room = Some(RoomSelection {
-                addr: self.sorted_room_list[0],
-                state_addr,
-            });
+    /// Start editing from the first room, with nothing to undo.
+    fn reset_selection(&mut self) {
+        self.history.clear();
+        self.level_editor.clear_reported_icons();
 
-            let state = self.sm.states[&(state_addr as usize)];
-            let tileset_index = state.tileset as usize;
-            self.selected_tileset = Some(TilesetSelection {
-                index: tileset_index,
-                data: self.sm.tilesets[tileset_index],
-            });
-        }
+        self.sorted_room_list = self.sm.rooms.keys().map(|value| *value).collect();
+        self.sorted_room_list.sort();
+
+        let room = &self.sm.rooms[&self.sorted_room_list[0]];
+        let state_addr = room.state_conditions[0].state_address as usize;
+
+        self.selected_room = Some(RoomSelection {
+            addr: self.sorted_room_list[0],
+            state_addr,
+        });
+
+        let state = self.sm.states[&(state_addr as usize)];
+        let tileset_index = state.tileset as usize;
+        self.selected_tileset = Some(TilesetSelection {
+            index: tileset_index,
+            data: self.sm.tilesets[tileset_index],
+        });
     }
 
     fn load_from_file(&self) {
         super::app::execute_async(async move {
             if let Some(file) = rfd::AsyncFileDialog::new().pick_file().await {
-                let file_data = file.read().await;
-                *SELECTED_FILE_DATA.lock().unwrap() = Some(file_data);
+                *SELECTED_FILE.lock().unwrap() = Some(SelectedFile {
+                    path: file_path(&file),
+                    data: file.read().await,
+                });
             }
         });
     }
 
-    fn save_to_file(&mut self) {
+    /// Open a ROM from the recent ROMs, or given at startup.
+    fn open_rom(&mut self, path: String) {
+        match read_rom(&path) {
+            Ok(data) => *SELECTED_FILE.lock().unwrap() = Some(SelectedFile { path, data }),
+            Err(e) => {
+                notifications::error(format!("Failed to open {}: {}", path, e));
+                self.recent_roms.remove(&path);
+            }
+        }
+    }
+
+    /// Write the edits into `sm.rom`.
+    fn build_rom(&mut self) {
         self.sm.save_to_rom();
-        super::app::save_file(&self.sm.rom);
+        self.room_data.save(&mut self.sm.rom);
+        rom::fix_checksum(&mut self.sm.rom);
+    }
+
+    /// Returns whether the ROM was saved.
+    fn save_to_file(&mut self) -> bool {
+        self.build_rom();
+
+        let data = match &self.copier_header {
+            Some(header) if self.keep_copier_header => [header, &self.sm.rom[..]].concat(),
+            _ => self.sm.rom.clone(),
+        };
+        let saved = super::app::save_file(&data, "Super_Metroid_Hack.smc");
+        if saved {
+            self.history.mark_saved();
+        }
+        saved
+    }
+
+    /// Patch from the ROM as loaded to the edited one.
+    fn export_patch(&mut self, format: PatchFormat) {
+        self.build_rom();
+
+        match patch::create_patch(format, &self.base_rom, &self.sm.rom) {
+            Ok(data) => {
+                let file_name = format!("Super_Metroid_Hack.{}", format.extension());
+                super::app::save_file(&data, &file_name);
+            }
+            Err(e) => notifications::error(format!("Failed to export the patch: {}", e)),
+        }
+    }
+
+    /// Edit the ROM as loaded with a patch applied, so patches can be exported from it again.
+    fn apply_patch(&mut self, ctx: &Context, file_name: &str, data: &[u8]) {
+        let Some(format) = PatchFormat::from_file_name(file_name) else {
+            notifications::error(format!("Unknown patch format: {}", file_name));
+            return;
+        };
+
+        match patch::apply_patch(format, &self.base_rom, data) {
+            Ok(rom_data) => {
+                if self.load_unheadered_rom(rom_data) {
+                    self.reload_textures(ctx);
+                    notifications::info(format!("Applied {}", file_name));
+                }
+            }
+            Err(e) => notifications::error(format!("Failed to apply {}: {}", file_name, e)),
+        }
+    }
+
+    /// Run `action` right away, or once the user confirms that unsaved edits can be lost.
+    fn confirm_unsaved(&mut self, ctx: &Context, action: UnsavedAction) {
+        if self.history.modified().is_empty() {
+            self.run_unsaved_action(ctx, action);
+        } else {
+            self.unsaved_prompt = Some(action);
+        }
+    }
+
+    fn run_unsaved_action(&mut self, ctx: &Context, action: UnsavedAction) {
+        match action {
+            UnsavedAction::LoadFromFile => self.load_from_file(),
+            UnsavedAction::OpenRom(path) => self.open_rom(path),
+            UnsavedAction::ApplyPatch => Self::pick_import_file(
+                ImportKind::Patch,
+                "Patch",
+                PatchFormat::ALL.map(|format| format.extension()).to_vec(),
+            ),
+            #[cfg(feature = "persistence")]
+            UnsavedAction::OpenProject => Self::pick_import_file(
+                ImportKind::Project,
+                "Zen SM project",
+                vec![project::EXTENSION],
+            ),
+            UnsavedAction::Close => {
+                self.closing = true;
+                ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+            }
+        }
+    }
+
+    fn pick_import_file(kind: ImportKind, filter: &'static str, extensions: Vec<&'static str>) {
+        super::app::execute_async(async move {
+            let dialog = rfd::AsyncFileDialog::new().add_filter(filter, &extensions);
+            if let Some(file) = dialog.pick_file().await {
+                *IMPORTED_FILE.lock().unwrap() = Some(FileImport {
+                    kind,
+                    file_name: file.file_name(),
+                    data: file.read().await,
+                });
+            }
+        });
+    }
+
+    fn import_palette(
+        &mut self,
+        ctx: &Context,
+        file_name: &str,
+        data: &[u8],
+        palette_address: usize,
+        sub_palette: Option<usize>,
+    ) {
+        let Some(palette) = self.sm.palettes.get_mut(&palette_address) else {return};
+
+        let Some(format) = PaletteFormat::from_file_name(file_name) else {
+            notifications::error(format!("Unknown palette format: {}", file_name));
+            return;
+        };
+        let colors = match palette_format::import_colors(data, format) {
+            Ok(colors) => colors,
+            Err(e) => {
+                notifications::error(format!("{}: {}", file_name, e));
+                return;
+            }
+        };
+
+        let old = *palette;
+        palette_format::set_palette_colors(palette, sub_palette, &colors);
+        self.history.push(Operation::Palette {
+            palette_address,
+            color: None,
+            old,
+            new: *palette,
+        });
+        self.reload_palette_colors(ctx);
+    }
+
+    fn import_graphics(&mut self, ctx: &Context, file_name: &str, data: &[u8]) {
+        let Some(tileset) = self.selected_tileset else {return};
+        let Some(palette) = self.sm.palettes.get(&(tileset.data.palette as usize)) else {return};
+        let graphics_address = tileset.data.graphic as usize;
+        let Some(graphics) = self.sm.graphics.get_mut(&graphics_address) else {return};
+
+        // Colors of a PNG that isn't indexed are matched to the sub palette being shown.
+        let sub_palette = self.graphics_editor.sub_palette();
+        let colors = palette_format::palette_colors(palette, Some(sub_palette));
+        let import = match graphics_format::import_png(data, &colors) {
+            Ok(import) => import,
+            Err(e) => {
+                notifications::error(format!("{}: {}", file_name, e));
+                return;
+            }
+        };
+
+        let size = graphics.size();
+        if import.size != size {
+            notifications::error(format!(
+                "{}: the image is {}x{} pixels, but the graphics are {}x{}",
+                file_name, import.size[0], import.size[1], size[0], size[1]
+            ));
+            return;
+        }
+        if !import.unmapped.is_empty() {
+            let unmapped: Vec<String> = import
+                .unmapped
+                .iter()
+                .map(|color| format!("#{:02X}{:02X}{:02X}", color.r, color.g, color.b))
+                .collect();
+            notifications::warning(format!(
+                "{}: colors not in sub palette {} were replaced by the nearest ones: {}",
+                file_name,
+                sub_palette,
+                unmapped.join(", ")
+            ));
+        }
+
+        let old = graphics.to_indexed_colors();
+        let edits: Vec<PixelEdit> = old
+            .iter()
+            .zip(import.indexes)
+            .enumerate()
+            .filter(|(_, (old, new))| **old as u8 != *new)
+            .map(|(pixel, (old, new))| PixelEdit {
+                position: [pixel % size[0], pixel / size[0]],
+                old: *old as u8,
+                new,
+            })
+            .collect();
+        if edits.is_empty() {
+            return;
+        }
+
+        for edit in edits.iter() {
+            history::write_pixel(graphics, edit.position, edit.new);
+        }
+        self.history.push(Operation::Graphics {
+            graphics_address,
+            stroke: self.graphics_editor.new_stroke(),
+            edits,
+        });
+        self.reload_tileset_textures(ctx);
+        self.reload_level_texture(ctx);
+    }
+
+    fn export_graphics(&self) {
+        let Some(tileset) = self.selected_tileset else {return};
+        let Some(palette) = self.sm.palettes.get(&(tileset.data.palette as usize)) else {return};
+        let graphics_address = tileset.data.graphic as usize;
+        let Some(graphics) = self.sm.graphics.get(&graphics_address) else {return};
+
+        let sub_palette = self.graphics_editor.sub_palette();
+        let colors = palette_format::palette_colors(palette, Some(sub_palette));
+        let indexes: Vec<u8> = graphics
+            .to_indexed_colors()
+            .iter()
+            .map(|index| *index as u8)
+            .collect();
+        match graphics_format::export_png(&indexes, graphics.size(), &colors) {
+            Ok(png) => {
+                let file_name = format!("gfx_{:X}.png", rom::pc_to_snes(graphics_address));
+                super::app::save_file(&png, &file_name);
+            }
+            Err(e) => notifications::error(format!("Failed to export the graphics: {}", e)),
+        }
+    }
+
+    fn export_room_image(&self) {
+        let Some(selected_room) = self.selected_room else {return};
+
+        let image = export::room_image(
+            &self.sm,
+            selected_room.addr,
+            selected_room.state_addr,
+            &EDITOR_ASSETS.lock().unwrap(),
+            self.room_image_options,
+        );
+        match image.map(|image| export::encode_png(&image)) {
+            Some(Ok(png)) => {
+                let file_name = format!("room_{:X}.png", rom::pc_to_snes(selected_room.addr));
+                super::app::save_file(&png, &file_name);
+            }
+            Some(Err(e)) => notifications::error(format!("Failed to export the room: {}", e)),
+            None => (),
+        }
+    }
+}
+
+// Project files.
+#[cfg(feature = "persistence")]
+impl ZenSM {
+    fn save_project(&mut self) {
+        let json = Project::diff(&self.base_rom, &self.sm, &self.room_data)
+            .and_then(|project| project.to_json());
+        match json {
+            Ok(json) => {
+                let file_name = format!("Super_Metroid_Hack.{}", project::EXTENSION);
+                if super::app::save_file(&json, &file_name) {
+                    self.history.mark_saved();
+                }
+            }
+            Err(e) => notifications::error(format!("Failed to save the project: {}", e)),
+        }
+    }
+
+    /// Rebuild the hack of a project from the loaded ROM, which must be the one it was made from.
+    fn open_project(&mut self, ctx: &Context, file_name: &str, data: &[u8]) {
+        if self.base_rom.is_empty() {
+            notifications::error("Load the ROM the project was made from, before opening it");
+            return;
+        }
+
+        let built = Project::from_json(data).and_then(|project| project.build(&self.base_rom));
+        match built {
+            Ok((sm, room_data)) => {
+                self.sm = sm;
+                self.room_data = room_data;
+                self.reset_selection();
+                self.reload_textures(ctx);
+                notifications::info(format!("Opened the project {}", file_name));
+            }
+            Err(e) => notifications::error(format!("Failed to open {}: {}", file_name, e)),
+        }
+    }
+}
+
+// Room manipulation.
+impl ZenSM {
+    fn select_room(&mut self, ctx: &Context, room_address: usize) {
+        let Some(room) = self.sm.rooms.get(&room_address) else {return};
+        let state_addr = room.state_conditions[0].state_address as usize;
+        self.selected_room = Some(RoomSelection {
+            addr: room_address,
+            state_addr,
+        });
+
+        let tileset = self.sm.states[&state_addr].tileset as usize;
+        self.selected_tileset = Some(TilesetSelection {
+            index: tileset,
+            data: self.sm.tilesets[tileset],
+        });
+
+        self.door_list.unselect();
+        self.enemy_editor.unselect();
+        self.plm_editor.unselect();
+        self.reload_textures(ctx);
+    }
+
+    fn apply_room_header(&mut self, ctx: &Context, room_address: usize, header: RoomHeader) {
+        let room = self.sm.rooms.get_mut(&room_address).unwrap();
+        let old = RoomHeader::from_room(room);
+
+        // Every state of the room shares its size, so resize all of their levels.
+        let mut levels: Vec<LevelReplacement> = Vec::new();
+        if header.size_in_blocks() != old.size_in_blocks() {
+            for state_condition in room.state_conditions.iter() {
+                let state = self.sm.states[&(state_condition.state_address as usize)];
+                let level_address = state.level_address as usize;
+                if levels
+                    .iter()
+                    .any(|level| level.level_address == level_address)
+                {
+                    continue;
+                }
+
+                let old_level = self.sm.levels[&level_address].clone();
+                let new_level =
+                    room::resize_level(&old_level, old.size_in_blocks(), header.size_in_blocks());
+                self.sm.levels.insert(level_address, new_level.clone());
+                levels.push(LevelReplacement {
+                    level_address,
+                    old: old_level,
+                    new: new_level,
+                });
+            }
+        }
+
+        // The scroll data of the states is laid out by screen, so it follows the new size too.
+        let mut scrolls: Vec<ScrollsReplacement> = Vec::new();
+        if header.size_in_screens() != old.size_in_screens() {
+            for state_condition in room.state_conditions.iter() {
+                let state_address = state_condition.state_address as usize;
+                let Some(&address) = self.room_data.state_scrolls.get(&state_address) else {continue};
+                if scrolls.iter().any(|scrolls| scrolls.address == address) {
+                    continue;
+                }
+
+                let old_scrolls = self.room_data.scrolls[&address].clone();
+                let new_scrolls = room::resize_scrolls(
+                    &old_scrolls,
+                    old.size_in_screens(),
+                    header.size_in_screens(),
+                );
+                self.room_data.scrolls.insert(address, new_scrolls.clone());
+                scrolls.push(ScrollsReplacement {
+                    address,
+                    old: old_scrolls,
+                    new: new_scrolls,
+                });
+            }
+        }
+
+        header.write(room);
+        self.history.push(Operation::Room {
+            room_address,
+            old,
+            new: header,
+            levels,
+            scrolls,
+        });
+
+        self.level_editor.clear_selection();
+        self.reload_level_texture(ctx);
+    }
+}
+
+// Undo and redo.
+impl ZenSM {
+    fn undo(&mut self, ctx: &Context) {
+        if let Some(affected) = self.history.undo(&mut self.sm, &mut self.room_data) {
+            self.sync_history(ctx, affected);
+        }
+    }
+
+    fn redo(&mut self, ctx: &Context) {
+        if let Some(affected) = self.history.redo(&mut self.sm, &mut self.room_data) {
+            self.sync_history(ctx, affected);
+        }
+    }
+
+    fn sync_history(&mut self, ctx: &Context, affected: Affected) {
+        match affected {
+            Affected::Level { level_address } => {
+                let Some(selected_room) = self.selected_room else {return};
+                let state = self.sm.states[&selected_room.state_addr];
+                if state.level_address as usize == level_address {
+                    self.reload_level_texture(ctx);
+                }
+            }
+            Affected::Palette { palette_address } => {
+                let Some(tileset) = self.selected_tileset else {return};
+                if tileset.data.palette as usize == palette_address {
+                    self.reload_palette_colors(ctx);
+                }
+            }
+            Affected::Tileset => {
+                if let Some(selected_room) = self.selected_room {
+                    let tileset = self.sm.states[&selected_room.state_addr].tileset as usize;
+                    self.selected_tileset = Some(TilesetSelection {
+                        index: tileset,
+                        data: self.sm.tilesets[tileset],
+                    });
+                } else if let Some(tileset) = self.selected_tileset.as_mut() {
+                    tileset.data = self.sm.tilesets[tileset.index];
+                }
+                self.reload_textures(ctx);
+            }
+            Affected::Graphics { graphics_address } => {
+                let Some(tileset) = self.selected_tileset else {return};
+                if tileset.data.graphic as usize == graphics_address {
+                    self.reload_tileset_textures(ctx);
+                    self.reload_level_texture(ctx);
+                }
+            }
+            Affected::TileTable { tile_table_address } => {
+                let Some(tileset) = self.selected_tileset else {return};
+                if tileset.data.tile_table as usize == tile_table_address {
+                    self.reload_tile_table_texture(ctx);
+                    self.reload_level_texture(ctx);
+                }
+            }
+            Affected::Room => self.reload_level_texture(ctx),
+            // Room data is drawn every frame straight from where it is stored.
+            Affected::Doors | Affected::Enemies | Affected::Plms | Affected::Scrolls => (),
+        }
     }
 }
 
@@ -160,9 +869,14 @@ impl ZenSM {
 impl ZenSM {
     fn reload_textures(&mut self, ctx: &Context) {
         self.level_editor.clear_selection();
+        self.reload_tileset_textures(ctx);
+        self.reload_level_texture(ctx);
+    }
 
+    /// Reload the palette, graphics and tile table views of the selected tileset.
+    fn reload_tileset_textures(&mut self, ctx: &Context) {
         if let Some(selected_tileset) = self.selected_tileset {
-            let (palette, graphics, tile_table) = self.sm.get_tileset_data(selected_tileset.index);
+            let (palette, graphics, _) = self.sm.get_tileset_data(selected_tileset.index);
 
             self.palette_editor.load_texture(ctx, palette.to_colors());
             self.graphics_editor.load_texture(
@@ -178,15 +892,30 @@ impl ZenSM {
                 palette,
                 graphics.size(),
             );
-            self.tiletable_editor.load_texture(
-                ctx,
-                tileset_to_indexed_colors(&tile_table, &graphics),
-                palette,
-                tileset_size(),
-            );
         }
+        self.reload_tile_table_texture(ctx);
+    }
 
-        self.reload_level_texture(ctx);
+    fn reload_tile_table_texture(&mut self, ctx: &Context) {
+        let Some(selected_tileset) = self.selected_tileset else {return};
+        self.load_tile_table_texture(ctx);
+
+        let (palette, _, tile_table) = self.sm.get_tileset_data(selected_tileset.index);
+        self.graphics_editor
+            .set_tile_sub_palettes(&tile_table, palette);
+    }
+
+    /// Same as `reload_tile_table_texture`, for edits that only change the graphics.
+    fn load_tile_table_texture(&mut self, ctx: &Context) {
+        let Some(selected_tileset) = self.selected_tileset else {return};
+        let (palette, graphics, tile_table) = self.sm.get_tileset_data(selected_tileset.index);
+
+        self.tiletable_editor.load_texture(
+            ctx,
+            tileset_to_indexed_colors(&tile_table, &graphics),
+            palette,
+            tileset_size(),
+        );
     }
 
     fn reload_level_texture(&mut self, ctx: &Context) {
@@ -200,14 +929,112 @@ impl ZenSM {
         let size = room.size_in_pixels();
         let indexed_colors = level_data.to_indexed_colors(room.size(), &tile_table, &graphics);
 
-        self.level_editor
-            .load_level(ctx, level_data, indexed_colors, *palette, size);
+        // Render Layer 2 through the same path as Layer 1, when the room has a custom one.
+        let layer2_indexed_colors = (!level_data.layer2.is_empty()).then(|| {
+            let mut layer2_data = level_data.clone();
+            layer2_data.layer1 = level_data.layer2.clone();
+            layer2_data.to_indexed_colors(room.size(), &tile_table, &graphics)
+        });
+
+        self.level_editor.load_level(
+            ctx,
+            level_data,
+            indexed_colors,
+            layer2_indexed_colors,
+            *palette,
+            size,
+        );
+    }
+
+    fn reload_palette_colors(&mut self, ctx: &Context) {
+        let Some(tileset) = self.selected_tileset else {return};
+        let Some(palette) = self.sm.palettes.get(&(tileset.data.palette as usize)) else {return};
+
+        self.palette_editor.load_texture(ctx, palette.to_colors());
+        self.graphics_editor.apply_colors(palette);
+        self.tiletable_editor.apply_colors(palette);
+        self.level_editor.apply_colors(palette);
     }
 }
 
 // Drawing functions.
 impl ZenSM {
-    fn draw_menu(ui: &mut Ui) -> Menu {
+    /// Shown until a ROM is loaded.
+    fn draw_welcome(&mut self, ui: &mut Ui) {
+        ui.vertical_centered(|ui| {
+            ui.heading("Open a Super Metroid ROM");
+            if ui.button("Load ROM from file").clicked() {
+                self.load_from_file();
+            }
+
+            if self.recent_roms.paths().is_empty() {
+                return;
+            }
+            ui.separator();
+            ui.label("Recent ROMs");
+            let mut opened = None;
+            for path in self.recent_roms.paths() {
+                if ui.link(path).clicked() {
+                    opened = Some(path.clone());
+                }
+            }
+            if let Some(path) = opened {
+                self.open_rom(path);
+            }
+        });
+    }
+
+    fn update_title(&mut self, ctx: &Context) {
+        let modified = !self.history.modified().is_empty();
+        if modified != self.title_modified {
+            self.title_modified = modified;
+            let title = if modified { "Zen SM *" } else { "Zen SM" };
+            ctx.send_viewport_cmd(egui::ViewportCommand::Title(title.to_string()));
+        }
+    }
+
+    fn draw_unsaved_prompt(&mut self, ctx: &Context) {
+        if self.unsaved_prompt.is_none() {
+            return;
+        }
+
+        let mut choice = None;
+        egui::Window::new("Unsaved changes")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                let subsystems = self.history.modified().subsystems();
+                ui.label(format!("Unsaved changes to the {}.", subsystems.join(", ")));
+                ui.label("Save the ROM before continuing?");
+                ui.horizontal(|ui| {
+                    if ui.button("Save").clicked() {
+                        choice = Some(UnsavedChoice::Save);
+                    }
+                    if ui.button("Discard").clicked() {
+                        choice = Some(UnsavedChoice::Discard);
+                    }
+                    if ui.button("Cancel").clicked() {
+                        choice = Some(UnsavedChoice::Cancel);
+                    }
+                });
+            });
+
+        let Some(choice) = choice else {return};
+        let Some(action) = self.unsaved_prompt.take() else {return};
+        match choice {
+            // Saving can be cancelled too, in the save dialog.
+            UnsavedChoice::Save => {
+                if self.save_to_file() {
+                    self.run_unsaved_action(ctx, action);
+                }
+            }
+            UnsavedChoice::Discard => self.run_unsaved_action(ctx, action),
+            UnsavedChoice::Cancel => (),
+        }
+    }
+
+    fn draw_menu(&mut self, ui: &mut Ui) -> Menu {
         let mut selected_menu = Menu::None;
         egui::menu::bar(ui, |ui| {
             ui.menu_button("File", |ui| {
@@ -215,10 +1042,85 @@ impl ZenSM {
                     selected_menu = Menu::LoadFromFile;
                     ui.close_menu();
                 };
+                ui.add_enabled_ui(!self.recent_roms.paths().is_empty(), |ui| {
+                    ui.menu_button("Open recent", |ui| {
+                        for path in self.recent_roms.paths() {
+                            if ui.button(path).clicked() {
+                                selected_menu = Menu::OpenRecent(path.clone());
+                                ui.close_menu();
+                            }
+                        }
+                    });
+                });
                 if ui.button("Save ROM to file").clicked() {
                     selected_menu = Menu::SaveToFile;
                     ui.close_menu();
                 };
+                #[cfg(feature = "persistence")]
+                {
+                    ui.separator();
+                    if ui.button("Open project").clicked() {
+                        selected_menu = Menu::OpenProject;
+                        ui.close_menu();
+                    };
+                    let save_project = egui::Button::new("Save project");
+                    if ui
+                        .add_enabled(self.selected_room.is_some(), save_project)
+                        .clicked()
+                    {
+                        selected_menu = Menu::SaveProject;
+                        ui.close_menu();
+                    };
+                    ui.separator();
+                }
+                ui.add_enabled(
+                    self.copier_header.is_some(),
+                    egui::Checkbox::new(&mut self.keep_copier_header, "Keep copier header"),
+                )
+                .on_hover_text("Save the ROM with the header it was loaded with");
+                ui.add_enabled_ui(self.selected_room.is_some(), |ui| {
+                    if ui.button("Apply patch").clicked() {
+                        selected_menu = Menu::ApplyPatch;
+                        ui.close_menu();
+                    };
+                    ui.menu_button("Export patch", |ui| {
+                        for format in PatchFormat::ALL {
+                            if ui.button(format.name()).clicked() {
+                                selected_menu = Menu::ExportPatch(format);
+                                ui.close_menu();
+                            }
+                        }
+                    });
+                });
+                ui.menu_button("Export room as PNG", |ui| {
+                    ui.checkbox(&mut self.room_image_options.bts, "BTS overlay");
+                    ui.checkbox(&mut self.room_image_options.layer2, "Layer 2");
+                    if ui.button("Export").clicked() {
+                        selected_menu = Menu::ExportRoomImage;
+                        ui.close_menu();
+                    };
+                });
+            });
+            ui.menu_button("Edit", |ui| {
+                let undo = egui::Button::new("Undo")
+                    .shortcut_text(ui.ctx().format_shortcut(&UNDO_SHORTCUT));
+                if ui.add_enabled(self.history.can_undo(), undo).clicked() {
+                    selected_menu = Menu::Undo;
+                    ui.close_menu();
+                };
+                let redo = egui::Button::new("Redo")
+                    .shortcut_text(ui.ctx().format_shortcut(&REDO_SHORTCUT));
+                if ui.add_enabled(self.history.can_redo(), redo).clicked() {
+                    selected_menu = Menu::Redo;
+                    ui.close_menu();
+                };
+            });
+            ui.menu_button("View", |ui| {
+                if ui.button("Error log").clicked() {
+                    selected_menu = Menu::ErrorLog;
+                    ui.close_menu();
+                };
+                ui.checkbox(&mut self.show_room_browser, "Room browser");
             });
         });
         selected_menu
@@ -227,24 +1129,126 @@ impl ZenSM {
     fn palette_editor(&mut self, ui: &mut Ui) {
         egui::ScrollArea::vertical().show(ui, |ui| {
             let Some(tileset) = self.selected_tileset else {return};
-            let Some(palette) = self.sm.palettes.get_mut(&(tileset.data.palette as usize)) else {return};
+            let palette_address = tileset.data.palette as usize;
+            let Some(palette) = self.sm.palettes.get_mut(&palette_address) else {return};
+
+            match self.palette_editor.file_menu(ui) {
+                PaletteFileCommand::Import(sub_palette) => {
+                    let extensions = PaletteFormat::ALL.map(|format| format.extension());
+                    ZenSM::pick_import_file(
+                        ImportKind::Palette(palette_address, sub_palette),
+                        "Palette",
+                        extensions.to_vec(),
+                    );
+                }
+                PaletteFileCommand::Export(format, sub_palette) => {
+                    let colors = palette_format::palette_colors(palette, sub_palette);
+                    match palette_format::export_colors(&colors, format) {
+                        Ok(data) => {
+                            let file_name = format!("palette.{}", format.extension());
+                            super::app::save_file(&data, &file_name);
+                        }
+                        Err(e) => {
+                            notifications::error(format!("Failed to export the palette: {}", e))
+                        }
+                    }
+                }
+                PaletteFileCommand::None => (),
+            }
 
-            let (response, _) = self.palette_editor.ui(ui, palette);
+            let old = *palette;
+            let (response, widget_rect) = self.palette_editor.ui(ui, palette);
+            if let Some(sub_palette) = self
+                .palette_editor
+                .clicked_sub_palette(&response, widget_rect)
+            {
+                self.graphics_editor.set_sub_palette(sub_palette, palette);
+            }
             if response.changed() {
-                self.palette_editor.load_texture(ui.ctx(), palette.to_colors());
-                self.graphics_editor.apply_colors(palette);
-                self.tiletable_editor.apply_colors(palette);
-                self.level_editor.apply_colors(palette);
+                if let Some(color) = self.palette_editor.selected_color() {
+                    self.history.push(Operation::Palette {
+                        palette_address,
+                        color: Some(color),
+                        old,
+                        new: *palette,
+                    });
+                }
+                self.reload_palette_colors(ui.ctx());
             }
         });
     }
 
     fn draw_graphics(&mut self, ui: &mut Ui) {
-        if !self.sm.graphics.is_empty() {
-            egui::ScrollArea::vertical().show(ui, |ui| {
-                self.graphics_editor.ui(ui);
-            });
+        let Some(tileset) = self.selected_tileset else {return};
+        let Some(palette) = self.sm.palettes.get(&(tileset.data.palette as usize)) else {return};
+
+        let command = egui::ScrollArea::vertical()
+            .show(ui, |ui| self.graphics_editor.ui(ui, palette))
+            .inner;
+        let (stroke, edits) = match command {
+            GraphicsCommand::Draw(stroke, edits) => (stroke, edits),
+            GraphicsCommand::TilePicked(tile) => {
+                let tile_table_address = tileset.data.tile_table as usize;
+                let Some(tile_table) = self.sm.tile_tables.get(&tile_table_address) else {return};
+                if let Some((block, new)) = self.block_composer.pick_tile(tile_table, tile) {
+                    self.apply_block_edit(ui.ctx(), block, new);
+                }
+                return;
+            }
+            GraphicsCommand::Import => {
+                ZenSM::pick_import_file(ImportKind::Graphics, "PNG", vec!["png"]);
+                return;
+            }
+            GraphicsCommand::Export => {
+                self.export_graphics();
+                return;
+            }
+            GraphicsCommand::None => return,
+        };
+
+        let graphics_address = tileset.data.graphic as usize;
+        let Some(graphics) = self.sm.graphics.get_mut(&graphics_address) else {return};
+        for edit in edits.iter() {
+            history::write_pixel(graphics, edit.position, edit.new);
         }
+        self.history.push(Operation::Graphics {
+            graphics_address,
+            stroke,
+            edits,
+        });
+
+        // The graphics view is already up to date, but the views built from it are not.
+        // Drawing sends one command by frame, so they are rebuilt at most once per frame.
+        self.load_tile_table_texture(ui.ctx());
+        self.reload_level_texture(ui.ctx());
+    }
+
+    fn draw_block_composer(&mut self, ui: &mut Ui) {
+        let Some(tileset) = self.selected_tileset else {return};
+        let Some(tile_table) = self.sm.tile_tables.get(&(tileset.data.tile_table as usize)) else {return};
+
+        if let Some((block, new)) = self.block_composer.ui(ui, tile_table) {
+            self.apply_block_edit(ui.ctx(), block, new);
+        }
+    }
+
+    fn apply_block_edit(&mut self, ctx: &Context, block: usize, new: [TileEntry; 4]) {
+        let Some(tileset) = self.selected_tileset else {return};
+        let tile_table_address = tileset.data.tile_table as usize;
+        let Some(tile_table) = self.sm.tile_tables.get_mut(&tile_table_address) else {return};
+
+        let old = widgets::read_block_entries(tile_table, block);
+        widgets::write_block_entries(tile_table, block, &new);
+        self.history.push(Operation::TileTable {
+            tile_table_address,
+            block,
+            corner: self.block_composer.corner(),
+            old,
+            new,
+        });
+
+        self.reload_tile_table_texture(ctx);
+        self.reload_level_texture(ctx);
     }
 
     fn draw_tile_table(&mut self, ui: &mut Ui) {
@@ -254,6 +1258,9 @@ impl ZenSM {
                     let Some(tileset) = self.selected_tileset else {return};
                     let Some(palette) = self.sm.palettes.get_mut(&(tileset.data.palette as usize)) else {return};
 
+                    if let Some((block, _)) = block_selection.data.first() {
+                        self.block_composer.set_block(block.block_number as usize);
+                    }
                     self.level_editor
                         .set_selection(ui.ctx(), block_selection, palette)
                 }
@@ -262,6 +1269,119 @@ impl ZenSM {
         }
     }
 
+    fn draw_room_properties(&mut self, ui: &mut Ui) {
+        let Some(selected_room) = self.selected_room else {return};
+
+        let header = RoomHeader::from_room(&self.sm.rooms[&selected_room.addr]);
+        if let Some(new_header) = self.room_properties.ui(ui, selected_room.addr, header) {
+            self.apply_room_header(ui.ctx(), selected_room.addr, new_header);
+        }
+    }
+
+    fn draw_doors(&mut self, ui: &mut Ui) {
+        let Some(selected_room) = self.selected_room else {return};
+
+        let doors = self.room_data.room_doors(selected_room.addr);
+        match self.door_list.ui(ui, &doors) {
+            DoorListCommand::Edited { address, old, new } => {
+                self.room_data.doors.insert(address, new);
+                self.history.push(Operation::Door { address, old, new });
+            }
+            DoorListCommand::Goto(room_address) => self.select_room(ui.ctx(), room_address),
+            DoorListCommand::None => (),
+        }
+    }
+
+    fn draw_enemies(&mut self, ui: &mut Ui) {
+        let Some(selected_room) = self.selected_room else {return};
+        let state_addr = selected_room.state_addr;
+
+        let population_address = self.room_data.state_enemy_populations.get(&state_addr);
+        let Some(&population_address) = population_address else {
+            ui.label("No enemy population");
+            return;
+        };
+        let enemy_set_address = self.room_data.state_enemy_sets.get(&state_addr).copied();
+
+        let population = &self.room_data.enemy_populations[&population_address];
+        let enemy_set =
+            enemy_set_address.map(|address| self.room_data.enemy_sets[&address].as_slice());
+        let command = self.enemy_editor.ui(ui, population, enemy_set);
+
+        self.apply_enemy_command(population_address, enemy_set_address, command);
+    }
+
+    fn apply_enemy_command(
+        &mut self,
+        population_address: usize,
+        enemy_set_address: Option<usize>,
+        command: EnemyCommand,
+    ) {
+        match command {
+            EnemyCommand::Population(edit, new) => {
+                let old = self
+                    .room_data
+                    .enemy_populations
+                    .insert(population_address, new.clone())
+                    .unwrap_or_default();
+                self.history.push(Operation::EnemyPopulation {
+                    address: population_address,
+                    edit,
+                    old,
+                    new,
+                });
+            }
+            EnemyCommand::EnemySet(edit, new) => {
+                let Some(address) = enemy_set_address else {return};
+                let old = self
+                    .room_data
+                    .enemy_sets
+                    .insert(address, new.clone())
+                    .unwrap_or_default();
+                self.history.push(Operation::EnemySet {
+                    address,
+                    edit,
+                    old,
+                    new,
+                });
+            }
+            EnemyCommand::None => (),
+        }
+    }
+
+    fn draw_plms(&mut self, ui: &mut Ui) {
+        let Some(selected_room) = self.selected_room else {return};
+
+        let plm_set_address = self.room_data.state_plm_sets.get(&selected_room.state_addr);
+        let Some(&address) = plm_set_address else {
+            ui.label("No PLM set");
+            return;
+        };
+
+        let plm_set = &self.room_data.plm_sets[&address];
+        if let Some((edit, new)) = self.plm_editor.ui(ui, plm_set) {
+            let old = self
+                .room_data
+                .plm_sets
+                .insert(address, new.clone())
+                .unwrap_or_default();
+            self.history.push(Operation::PlmSet {
+                address,
+                edit,
+                old,
+                new,
+            });
+        }
+    }
+
+    fn draw_bts_palette(&mut self, ui: &mut Ui) {
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            if let Some(selection) = self.bts_palette.ui(ui) {
+                self.level_editor.set_bts_brush(selection);
+            }
+        });
+    }
+
     fn tileset_selector(&mut self, ui: &mut Ui) {
         let Some(mut tileset) = self.selected_tileset else {return};
         let tileset_index = tileset.index;
@@ -279,11 +1399,13 @@ impl ZenSM {
             });
 
             if let Some(selected_room) = self.selected_room {
-                self.sm
-                    .states
-                    .get_mut(&selected_room.state_addr)
-                    .unwrap()
-                    .tileset = selection as u8;
+                let state = self.sm.states.get_mut(&selected_room.state_addr).unwrap();
+                self.history.push(Operation::StateTileset {
+                    state_address: selected_room.state_addr,
+                    old: state.tileset,
+                    new: selection as u8,
+                });
+                state.tileset = selection as u8;
             }
             self.reload_textures(ui.ctx());
         };
@@ -297,6 +1419,11 @@ impl ZenSM {
             tileset.data.palette = selection as u32;
             self.selected_tileset = Some(tileset);
 
+            self.history.push(Operation::Tileset {
+                index: tileset_index,
+                old: self.sm.tilesets[tileset_index],
+                new: tileset.data,
+            });
             self.sm.tilesets[tileset_index].palette = selection as u32;
             self.reload_textures(ui.ctx());
         }
@@ -310,6 +1437,11 @@ impl ZenSM {
             tileset.data.graphic = selection as u32;
             self.selected_tileset = Some(tileset);
 
+            self.history.push(Operation::Tileset {
+                index: tileset_index,
+                old: self.sm.tilesets[tileset_index],
+                new: tileset.data,
+            });
             self.sm.tilesets[tileset_index].graphic = selection as u32;
             self.reload_textures(ui.ctx());
         };
@@ -323,32 +1455,59 @@ impl ZenSM {
             tileset.data.tile_table = selection as u32;
             self.selected_tileset = Some(tileset);
 
+            self.history.push(Operation::Tileset {
+                index: tileset_index,
+                old: self.sm.tilesets[tileset_index],
+                new: tileset.data,
+            });
             self.sm.tilesets[tileset_index].tile_table = selection as u32;
             self.reload_textures(ui.ctx());
         };
     }
 
+    fn draw_room_browser(&mut self, ui: &mut Ui) {
+        let modified = self.history.modified();
+        let modified_rooms: HashSet<usize> = self
+            .sorted_room_list
+            .iter()
+            .copied()
+            .filter(|room| modified.is_room_modified(&self.sm, &self.room_data, *room))
+            .collect();
+
+        let selected = self.selected_room.map(|selection| selection.addr);
+        let rooms = &self.sorted_room_list;
+        let command = self
+            .room_browser
+            .ui(ui, &self.sm, rooms, selected, &modified_rooms);
+        if let RoomBrowserCommand::Select(room_address) = command {
+            self.select_room(ui.ctx(), room_address);
+        }
+    }
+
     fn draw_room_selector(&mut self, ui: &mut Ui) {
         let Some(selected_room) = self.selected_room else {return};
 
-        if let Some(selection) =
-            ZenSM::draw_combo_box(ui, "Room", self.sorted_room_list.iter(), selected_room.addr)
-        {
-            let room = &self.sm.rooms[&selection];
-            let state_addr = room.state_conditions[0].state_address as usize;
-            self.selected_room = Some(RoomSelection {
-                addr: selection,
-                state_addr,
-            });
+        // Rooms with unsaved edits are marked with an asterisk.
+        let modified = self.history.modified();
+        let room_label = |room_address: usize| {
+            let marker = match modified.is_room_modified(&self.sm, &self.room_data, room_address) {
+                true => " *",
+                false => "",
+            };
+            format!("{}{}", self.room_browser.label(room_address), marker)
+        };
 
-            let tileset = self.sm.states[&state_addr].tileset as usize;
-            self.selected_tileset = Some(TilesetSelection {
-                index: tileset,
-                data: self.sm.tilesets[tileset],
+        let mut selection = selected_room.addr;
+        egui::ComboBox::from_label("Room")
+            .selected_text(room_label(selected_room.addr))
+            .show_ui(ui, |ui| {
+                for room_address in self.sorted_room_list.iter() {
+                    ui.selectable_value(&mut selection, *room_address, room_label(*room_address));
+                }
             });
-
-            self.reload_textures(ui.ctx());
-        };
+        if selection != selected_room.addr {
+            self.select_room(ui.ctx(), selection);
+        }
 
         let room = &self.sm.rooms[&selected_room.addr];
         if let Some(selection) = ZenSM::draw_combo_box(
@@ -362,6 +1521,8 @@ impl ZenSM {
             selected_room.state_addr,
         ) {
             self.selected_room.as_mut().unwrap().state_addr = selection;
+            self.enemy_editor.unselect();
+            self.plm_editor.unselect();
 
             let tileset = self.sm.states[&selection].tileset as usize;
             self.selected_tileset = Some(TilesetSelection {
@@ -379,15 +1540,122 @@ impl ZenSM {
         let Some(selected_room) = self.selected_room else {return};
 
         let state = self.sm.states[&selected_room.state_addr];
+        let room = &self.sm.rooms[&selected_room.addr];
+        let size_in_screens = [room.width as usize, room.height as usize];
         let level = self
             .sm
             .levels
             .get_mut(&(state.level_address as usize))
             .unwrap();
 
-        egui::ScrollArea::both().show(ui, |ui| {
-            self.level_editor.ui(ui, level, palette);
-        });
+        self.level_editor.toolbar(ui, palette);
+        self.level_editor
+            .set_highlighted_door(self.door_list.selected());
+
+        let population_address = self
+            .room_data
+            .state_enemy_populations
+            .get(&selected_room.state_addr)
+            .copied();
+        let plm_set_address = self
+            .room_data
+            .state_plm_sets
+            .get(&selected_room.state_addr)
+            .copied();
+        let scrolls_address = self
+            .room_data
+            .state_scrolls
+            .get(&selected_room.state_addr)
+            .copied();
+
+        // States without scroll data use a single scroll value for all of their screens.
+        let scrolls = match scrolls_address {
+            Some(address) => self.room_data.scrolls[&address].clone(),
+            None => {
+                let fixed_scroll = self.room_data.fixed_scrolls.get(&selected_room.state_addr);
+                let fixed_scroll = fixed_scroll.copied().unwrap_or(scroll::BLUE);
+                if self.level_editor.tool() == widgets::Tool::Scrolls {
+                    let text = format!(
+                        "This state has no scroll data to edit, all of its screens are {}",
+                        scroll::SCROLL_NAMES[fixed_scroll as usize].to_lowercase()
+                    );
+                    ui.colored_label(ui.visuals().warn_fg_color, text);
+                }
+                let screens = size_in_screens[0] * size_in_screens[1];
+                vec![fixed_scroll; screens]
+            }
+        };
+
+        let (edits, enemy_command, new_scrolls) = egui::ScrollArea::both()
+            .show(ui, |ui| {
+                let (response, rect, edits) = self.level_editor.ui(ui, level, palette);
+
+                let new_scrolls = if self.level_editor.scrolls_visible() {
+                    widgets::scroll_overlay(
+                        ui,
+                        &response,
+                        rect,
+                        size_in_screens,
+                        &scrolls,
+                        scrolls_address.is_some()
+                            && self.level_editor.tool() == widgets::Tool::Scrolls,
+                    )
+                } else {
+                    None
+                };
+
+                let plm_set = plm_set_address
+                    .filter(|_| self.level_editor.plms_visible())
+                    .map(|address| &self.room_data.plm_sets[&address]);
+                if let Some(plm_set) = plm_set {
+                    self.plm_editor
+                        .overlay(ui, rect, self.level_editor.editor.size(), plm_set);
+                }
+
+                let population = population_address
+                    .filter(|_| self.level_editor.enemies_visible())
+                    .map(|address| &self.room_data.enemy_populations[&address]);
+                let enemy_command = match population {
+                    Some(population) => self.enemy_editor.overlay(
+                        ui,
+                        &response,
+                        rect,
+                        self.level_editor.editor.size(),
+                        population,
+                        self.level_editor.tool() == widgets::Tool::Enemies,
+                    ),
+                    None => EnemyCommand::None,
+                };
+
+                (edits, enemy_command, new_scrolls)
+            })
+            .inner;
+
+        if let Some((layer, edits)) = edits {
+            self.history.push(Operation::Level {
+                level_address: state.level_address as usize,
+                layer,
+                edits,
+            });
+        }
+
+        if let Some(population_address) = population_address {
+            self.apply_enemy_command(population_address, None, enemy_command);
+        }
+
+        if let (Some(address), Some(new)) = (scrolls_address, new_scrolls) {
+            self.room_data.scrolls.insert(address, new.clone());
+            self.history.push(Operation::Scrolls {
+                address,
+                old: scrolls,
+                new,
+            });
+        }
+
+        // A block brush replaces the BTS brush.
+        if self.level_editor.bts_brush().is_none() {
+            self.bts_palette.unselect();
+        }
     }
 
     fn draw_combo_box<'a>(
@@ -421,20 +1689,46 @@ fn execute_async<F: Future<Output = ()> + 'static>(f: F) {
 }
 
 #[cfg(not(target_arch = "wasm32"))]
-fn save_file(data: &Vec<u8>) {
-    if let Some(file) = rfd::FileDialog::new().save_file() {
-        if let Err(e) = std::fs::write(file, data) {
-            println!("{:?}", e);
+fn file_path(file: &rfd::FileHandle) -> String {
+    file.path().display().to_string()
+}
+
+#[cfg(target_arch = "wasm32")]
+fn file_path(file: &rfd::FileHandle) -> String {
+    file.file_name()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn read_rom(path: &str) -> Result<Vec<u8>, String> {
+    std::fs::read(path).map_err(|e| e.to_string())
+}
+
+// Browsers can't read a file by its path, so use the copy kept in local storage.
+#[cfg(target_arch = "wasm32")]
+fn read_rom(name: &str) -> Result<Vec<u8>, String> {
+    crate::recent_roms::cached_rom(name)
+        .ok_or_else(|| "it is no longer in local storage".to_string())
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn save_file(data: &Vec<u8>, file_name: &str) -> bool {
+    let dialog = rfd::FileDialog::new().set_file_name(file_name);
+    let Some(file) = dialog.save_file() else {return false};
+    match std::fs::write(&file, data) {
+        Ok(()) => true,
+        Err(e) => {
+            notifications::error(format!("Failed to save {}: {}", file.display(), e));
+            false
         }
     }
 }
 
 #[cfg(target_arch = "wasm32")]
-fn save_file(data: &Vec<u8>) {
+fn save_file(data: &Vec<u8>, file_name: &str) -> bool {
     use wasm_bindgen::JsCast;
     use web_sys::BlobPropertyBag;
 
-    // Create a JsArray for the ROM data.
+    // Create a JsArray for the file data.
     let uint8arr = js_sys::Uint8Array::new(&unsafe { js_sys::Uint8Array::view(&data) }.into());
     let js_bytes = js_sys::Array::new();
     js_bytes.push(&uint8arr.buffer());
@@ -449,11 +1743,16 @@ fn save_file(data: &Vec<u8>) {
                     if let Some(element) = document.get_element_by_id("download_rom") {
                         let anchor = element.dyn_into::<web_sys::HtmlAnchorElement>().unwrap();
                         anchor.set_href(&url);
+                        anchor.set_download(file_name);
                         anchor.click();
+                        web_sys::Url::revoke_object_url(&url).unwrap();
+                        return true;
                     }
                 }
             }
             web_sys::Url::revoke_object_url(&url).unwrap();
         }
     }
+    notifications::error(format!("Failed to download {}", file_name));
+    false
 }
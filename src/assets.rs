@@ -6,6 +6,8 @@ use std::{
 use eframe::epaint::ColorImage;
 use zen::super_metroid::{level_data::BlockType, tile_table::BLOCK_SIZE};
 
+use crate::{notifications, room::PlmKind};
+
 #[derive(Default, Debug, Clone, Copy, Hash, Eq, PartialEq)]
 pub struct BtsTile {
     pub block_type: BlockType,
@@ -38,16 +40,21 @@ pub fn load_bts_icons(editor_assets: Arc<Mutex<HashMap<BtsTile, ColorImage>>>) {
             js_buffer.copy_to(&mut buffer);
 
             let result = image::load_from_memory(&buffer);
-            if let Ok(image) = result {
-                let size = [image.width() as _, image.height() as _];
-                let image_buffer = image.to_rgba8();
-                let pixels = image_buffer.as_flat_samples();
+            match result {
+                Ok(image) => {
+                    let size = [image.width() as _, image.height() as _];
+                    let image_buffer = image.to_rgba8();
+                    let pixels = image_buffer.as_flat_samples();
 
-                let file_name = asset.replace(".png", "");
+                    let file_name = asset.replace(".png", "");
 
-                let bts_icon = ColorImage::from_rgba_unmultiplied(size, pixels.as_slice());
+                    let bts_icon = ColorImage::from_rgba_unmultiplied(size, pixels.as_slice());
 
-                generate_bts_tiles(&editor_assets, bts_icon, &file_name);
+                    if let Err(e) = generate_bts_tiles(&editor_assets, bts_icon, &file_name) {
+                        notifications::warning(e);
+                    }
+                }
+                Err(e) => notifications::warning(format!("Failed to load images/{}: {}", asset, e)),
             }
         }
     });
@@ -55,14 +62,89 @@ pub fn load_bts_icons(editor_assets: Arc<Mutex<HashMap<BtsTile, ColorImage>>>) {
 
 #[cfg(not(target_arch = "wasm32"))]
 pub fn load_bts_icons(editor_assets: Arc<Mutex<HashMap<BtsTile, ColorImage>>>) {
-    let paths = std::fs::read_dir("images").unwrap();
-    for path in paths {
-        let path = path.unwrap().path();
-        let file_name = path.file_stem().unwrap().to_str().unwrap();
+    if let Err(e) = read_bts_icons(&editor_assets) {
+        notifications::error(e);
+    }
+}
+
+/// Load the BTS icons of `images/`, like `load_bts_icons`, but returning the failures
+/// instead of notifying them. Icons that fail are skipped, and reported together.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn read_bts_icons(
+    editor_assets: &Arc<Mutex<HashMap<BtsTile, ColorImage>>>,
+) -> Result<(), String> {
+    let paths = std::fs::read_dir("images")
+        .map_err(|e| format!("Failed to read the BTS icons in images/: {}", e))?;
+
+    let mut errors = Vec::new();
+    for path in paths.flatten() {
+        let path = path.path();
+        if !path.is_file() {
+            continue; // Like `images/plm/`, loaded by `load_plm_icons`.
+        }
+        let Some(file_name) = path.file_stem().and_then(|name| name.to_str()) else {continue};
+
+        let result = load_image_from_path(&path)
+            .map_err(|e| format!("Failed to load {}: {}", path.display(), e))
+            .and_then(|bts_icon| generate_bts_tiles(editor_assets, bts_icon, file_name));
+        if let Err(e) = result {
+            errors.push(e);
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors.join("\n"))
+    }
+}
+
+/// PLM icons are named after their kind, in `images/plm/`.
+fn plm_icon_path(kind: PlmKind) -> String {
+    let name = match kind {
+        PlmKind::Item => "item",
+        PlmKind::Door => "door",
+        PlmKind::Station => "station",
+        PlmKind::Scroll => "scroll",
+        PlmKind::Other => "other",
+    };
+    format!("images/plm/{}.png", name)
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn load_plm_icons(plm_icons: Arc<Mutex<HashMap<PlmKind, ColorImage>>>) {
+    wasm_bindgen_futures::spawn_local(async move {
+        for kind in PlmKind::ALL {
+            let path = plm_icon_path(kind);
+            let js_buffer: js_sys::Uint8Array = fetch_image(&path).await.into();
 
-        let bts_icon = load_image_from_path(&path).unwrap();
+            let mut buffer = vec![0; js_buffer.length() as usize];
+            js_buffer.copy_to(&mut buffer);
 
-        generate_bts_tiles(&editor_assets, bts_icon, file_name);
+            match image::load_from_memory(&buffer) {
+                Ok(image) => {
+                    let size = [image.width() as _, image.height() as _];
+                    let image_buffer = image.to_rgba8();
+                    let pixels = image_buffer.as_flat_samples();
+                    let plm_icon = ColorImage::from_rgba_unmultiplied(size, pixels.as_slice());
+                    plm_icons.lock().unwrap().insert(kind, plm_icon);
+                }
+                Err(e) => notifications::warning(format!("Failed to load {}: {}", path, e)),
+            }
+        }
+    });
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn load_plm_icons(plm_icons: Arc<Mutex<HashMap<PlmKind, ColorImage>>>) {
+    for kind in PlmKind::ALL {
+        let path = plm_icon_path(kind);
+        match load_image_from_path(std::path::Path::new(&path)) {
+            Ok(plm_icon) => {
+                plm_icons.lock().unwrap().insert(kind, plm_icon);
+            }
+            Err(e) => notifications::warning(format!("Failed to load {}: {}", path, e)),
+        }
     }
 }
 
@@ -79,14 +161,20 @@ fn generate_bts_tiles(
     editor_assets: &Arc<Mutex<HashMap<BtsTile, ColorImage>>>,
     bts_icon: ColorImage,
     file_name: &str,
-) {
-    let mut splitted_file_name = file_name.split("_");
+) -> Result<(), String> {
+    // Icons are named after their block type and BTS, in hexadecimal, like `1_0a`.
+    let mut splitted_file_name = file_name
+        .split("_")
+        .map(|value| u8::from_str_radix(value, 16));
+    let (Some(Ok(block_type)), Some(Ok(bts_block))) =
+        (splitted_file_name.next(), splitted_file_name.next())
+    else {
+        return Err(format!("Unexpected BTS icon name: {}", file_name));
+    };
 
     let bts_tile = BtsTile {
-        block_type: u8::from_str_radix(splitted_file_name.next().unwrap(), 16)
-            .unwrap()
-            .into(),
-        bts_block: u8::from_str_radix(splitted_file_name.next().unwrap(), 16).unwrap(),
+        block_type: block_type.into(),
+        bts_block,
     };
 
     if bts_tile.block_type == BlockType::Slope {
@@ -114,6 +202,7 @@ fn generate_bts_tiles(
     }
 
     editor_assets.lock().unwrap().insert(bts_tile, bts_icon);
+    Ok(())
 }
 
 fn image_mirror_horizontally(image: &ColorImage) -> ColorImage {
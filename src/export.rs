@@ -0,0 +1,157 @@
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, Cursor},
+    path::Path,
+};
+
+use eframe::epaint::{Color32, ColorImage};
+use image::{ImageError, ImageOutputFormat, Rgba, RgbaImage};
+use zen::{
+    graphics::Rgb888,
+    super_metroid::{tile_table::BLOCK_SIZE, SuperMetroid},
+};
+
+use crate::{assets::BtsTile, rom};
+
+#[derive(Debug, Clone, Copy)]
+pub struct RoomImageOptions {
+    pub bts: bool,    // Draw the block type and BTS icons over the level.
+    pub layer2: bool, // Draw Layer 2 behind Layer 1, when the room has a custom one.
+}
+
+impl Default for RoomImageOptions {
+    fn default() -> Self {
+        Self {
+            bts: false,
+            layer2: true,
+        }
+    }
+}
+
+/// Render a state of a room, as the level editor shows it.
+pub fn room_image(
+    sm: &SuperMetroid,
+    room_address: usize,
+    state_address: usize,
+    bts_icons: &HashMap<BtsTile, ColorImage>,
+    options: RoomImageOptions,
+) -> Option<RgbaImage> {
+    let room = sm.rooms.get(&room_address)?;
+    let state = sm.states.get(&state_address)?;
+    let (level_data, _, palette, graphics, tile_table) = sm.get_state_data(state);
+
+    let [width, height] = room.size_in_pixels();
+    let backdrop: Rgb888 = palette.sub_palettes[0].colors[0].into();
+    let mut image = RgbaImage::from_pixel(
+        width as u32,
+        height as u32,
+        Rgba([backdrop.r, backdrop.g, backdrop.b, 255]),
+    );
+
+    let mut layers = Vec::new();
+    if options.layer2 && !level_data.layer2.is_empty() {
+        let mut layer2_data = level_data.clone();
+        layer2_data.layer1 = level_data.layer2.clone();
+        layers.push(layer2_data.to_indexed_colors(room.size(), &tile_table, &graphics));
+    }
+    layers.push(level_data.to_indexed_colors(room.size(), &tile_table, &graphics));
+
+    // Color index 0 is transparent, showing the layer behind.
+    for indexed_colors in layers {
+        for (pixel, indexed_color) in image.pixels_mut().zip(indexed_colors) {
+            if indexed_color.index != 0 {
+                let color: Rgb888 = palette.sub_palettes[indexed_color.sub_palette].colors
+                    [indexed_color.index]
+                    .into();
+                *pixel = Rgba([color.r, color.g, color.b, 255]);
+            }
+        }
+    }
+
+    if options.bts {
+        let x_blocks = width / BLOCK_SIZE;
+        let blocks = level_data.layer1.iter().zip(level_data.bts.iter());
+        for (index, (block, bts_block)) in blocks.enumerate() {
+            let bts_tile = BtsTile {
+                block_type: block.block_type,
+                bts_block: *bts_block,
+            };
+            let Some(bts_icon) = bts_icons.get(&bts_tile) else {continue};
+
+            let position = [index % x_blocks, index / x_blocks].map(|p| p * BLOCK_SIZE);
+            draw_icon(&mut image, position, bts_icon);
+        }
+    }
+
+    Some(image)
+}
+
+/// Render every state of every room into `out_dir`, with an `index.json` listing the images.
+/// Returns the number of images written.
+pub fn export_all_rooms(
+    sm: &SuperMetroid,
+    out_dir: &Path,
+    bts_icons: &HashMap<BtsTile, ColorImage>,
+    options: RoomImageOptions,
+) -> io::Result<usize> {
+    fs::create_dir_all(out_dir)?;
+
+    let mut room_addresses: Vec<usize> = sm.rooms.keys().copied().collect();
+    room_addresses.sort();
+
+    let mut entries = Vec::new();
+    for room_address in room_addresses {
+        let room = &sm.rooms[&room_address];
+        for (index, state_condition) in room.state_conditions.iter().enumerate() {
+            let state_address = state_condition.state_address as usize;
+            let image = room_image(sm, room_address, state_address, bts_icons, options);
+            let Some(image) = image else {continue};
+
+            let room_pointer = rom::pc_to_snes(room_address);
+            let file_name = format!("room_{:X}_state_{}.png", room_pointer, index);
+            let png = encode_png(&image).map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+            fs::write(out_dir.join(&file_name), png)?;
+
+            entries.push(serde_json::json!({
+                "room": format!("{:X}", room_pointer),
+                "state": format!("{:X}", rom::pc_to_snes(state_address)),
+                "width": image.width(),
+                "height": image.height(),
+                "file": file_name,
+            }));
+        }
+    }
+
+    let index = serde_json::to_string_pretty(&entries)?;
+    fs::write(out_dir.join("index.json"), index)?;
+    Ok(entries.len())
+}
+
+pub fn encode_png(image: &RgbaImage) -> Result<Vec<u8>, ImageError> {
+    let mut png = Cursor::new(Vec::new());
+    image.write_to(&mut png, ImageOutputFormat::Png)?;
+    Ok(png.into_inner())
+}
+
+/// Blend an icon over the image, with its top left corner at `position`.
+fn draw_icon(image: &mut RgbaImage, [x, y]: [usize; 2], icon: &ColorImage) {
+    for (index, color) in icon.pixels.iter().enumerate() {
+        let [x, y] = [x + index % icon.size[0], y + index / icon.size[0]];
+        if x >= image.width() as usize || y >= image.height() as usize {
+            continue;
+        }
+
+        let pixel = image.get_pixel_mut(x as u32, y as u32);
+        blend(pixel, *color);
+    }
+}
+
+fn blend(pixel: &mut Rgba<u8>, color: Color32) {
+    // Colors of a `ColorImage` are premultiplied by their alpha.
+    let transparency = 255 - color.a() as u16;
+    let channels = [color.r(), color.g(), color.b()];
+    for (channel, source) in pixel.0.iter_mut().zip(channels) {
+        *channel = source.saturating_add((*channel as u16 * transparency / 255) as u8);
+    }
+}
@@ -0,0 +1,140 @@
+use std::io::Cursor;
+
+use png::{BitDepth, ColorType, Decoder, Encoder, Transformations};
+use zen::graphics::{palette::COLORS_BY_SUB_PALETTE, Rgb888};
+
+use super::palette::quantize;
+
+/// A graphics sheet read from a PNG, as color indexes of a sub palette.
+pub struct GraphicsImport {
+    pub size: [usize; 2],
+    pub indexes: Vec<u8>, // Color index of every pixel, row by row.
+    // Colors missing from the sub palette, drawn with the nearest color of it instead.
+    pub unmapped: Vec<Rgb888>,
+}
+
+/// Write a graphics sheet as a 4bpp indexed PNG, using the colors of a sub palette.
+/// Color index 0 is transparent.
+pub fn export_png(indexes: &[u8], size: [usize; 2], colors: &[Rgb888]) -> Result<Vec<u8>, String> {
+    let mut png = Vec::new();
+    let mut encoder = Encoder::new(&mut png, size[0] as u32, size[1] as u32);
+    encoder.set_color(ColorType::Indexed);
+    encoder.set_depth(BitDepth::Four);
+    encoder.set_palette(
+        colors
+            .iter()
+            .flat_map(|color| [color.r, color.g, color.b])
+            .collect::<Vec<u8>>(),
+    );
+    encoder.set_trns(vec![0]);
+
+    // Two pixels by byte, the leftmost in the high nibble.
+    // Each row starts on a new byte, so an odd width pads its last byte.
+    let data: Vec<u8> = indexes
+        .chunks(size[0].max(1))
+        .flat_map(|row| row.chunks(2))
+        .map(|pixels| (pixels[0] & 0x0F) << 4 | (pixels.get(1).unwrap_or(&0) & 0x0F))
+        .collect();
+
+    let mut writer = encoder.write_header().map_err(|e| e.to_string())?;
+    writer.write_image_data(&data).map_err(|e| e.to_string())?;
+    writer.finish().map_err(|e| e.to_string())?;
+    Ok(png)
+}
+
+/// Read a graphics sheet from a PNG. Indexed PNGs using up to 16 colors keep their indexes,
+/// other PNGs have their colors matched to the sub palette `colors`.
+pub fn import_png(bytes: &[u8], colors: &[Rgb888]) -> Result<GraphicsImport, String> {
+    if let Some(import) = read_indexes(bytes)? {
+        return Ok(import);
+    }
+
+    let image = image::load_from_memory(bytes)
+        .map_err(|e| e.to_string())?
+        .to_rgba8();
+
+    let palette: Vec<Rgb888> = colors.iter().map(|color| quantize(*color)).collect();
+    let mut unmapped = Vec::new();
+    let indexes = image
+        .pixels()
+        .map(|pixel| {
+            let [r, g, b, alpha] = pixel.0;
+            if alpha == 0 {
+                return 0;
+            }
+
+            let color = quantize(Rgb888 { r, g, b });
+            if let Some(index) = find_color(&palette, color) {
+                return index;
+            }
+            if !unmapped.contains(&color) {
+                unmapped.push(color);
+            }
+            nearest_color(&palette, color)
+        })
+        .collect();
+
+    Ok(GraphicsImport {
+        size: [image.width() as usize, image.height() as usize],
+        indexes,
+        unmapped,
+    })
+}
+
+/// The color indexes of an indexed PNG, if it is one that fits in a sub palette.
+fn read_indexes(bytes: &[u8]) -> Result<Option<GraphicsImport>, String> {
+    let mut decoder = Decoder::new(Cursor::new(bytes));
+    decoder.set_transformations(Transformations::IDENTITY);
+    let mut reader = decoder.read_info().map_err(|e| e.to_string())?;
+
+    let mut data = vec![0; reader.output_buffer_size()];
+    let frame = reader.next_frame(&mut data).map_err(|e| e.to_string())?;
+    if frame.color_type != ColorType::Indexed {
+        return Ok(None);
+    }
+
+    let bits = frame.bit_depth as usize;
+    let mask = ((1u16 << bits) - 1) as u8;
+    let [width, height] = [frame.width as usize, frame.height as usize];
+    let mut indexes = Vec::with_capacity(width * height);
+    for row in data.chunks(frame.line_size).take(height) {
+        for x in 0..width {
+            let bit = x * bits;
+            indexes.push((row[bit / 8] >> (8 - bits - bit % 8)) & mask);
+        }
+    }
+
+    if indexes
+        .iter()
+        .any(|index| *index as usize >= COLORS_BY_SUB_PALETTE)
+    {
+        return Ok(None);
+    }
+
+    Ok(Some(GraphicsImport {
+        size: [width, height],
+        indexes,
+        unmapped: Vec::new(),
+    }))
+}
+
+/// Opaque colors prefer the indexes past 0, which is transparent.
+fn find_color(palette: &[Rgb888], color: Rgb888) -> Option<u8> {
+    (1..palette.len())
+        .chain([0])
+        .find(|index| palette[*index] == color)
+        .map(|index| index as u8)
+}
+
+fn nearest_color(palette: &[Rgb888], color: Rgb888) -> u8 {
+    let distance = |other: &Rgb888| {
+        [(color.r, other.r), (color.g, other.g), (color.b, other.b)]
+            .iter()
+            .map(|(a, b)| (*a as i32 - *b as i32).pow(2))
+            .sum::<i32>()
+    };
+
+    (1..palette.len())
+        .min_by_key(|index| distance(&palette[*index]))
+        .unwrap_or(0) as u8
+}
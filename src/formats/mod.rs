@@ -0,0 +1,3 @@
+pub mod graphics;
+pub mod palette;
+pub mod patch;
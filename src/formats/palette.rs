@@ -0,0 +1,243 @@
+use std::io::Cursor;
+
+use image::{ImageOutputFormat, Rgb, RgbImage};
+use zen::graphics::{
+    palette::{COLORS_BY_SUB_PALETTE, NUMBER_OF_SUB_PALETTES},
+    Palette, Rgb888,
+};
+
+const TPL_HEADER: &[u8] = b"TPL";
+const TPL_RGB: u8 = 0;
+const TPL_SNES: u8 = 2;
+
+const ACT_COLORS: usize = 256;
+const GPL_HEADER: &str = "GIMP Palette";
+
+const SWATCH_SIZE: u32 = 8; // Pixels by color in PNG swatches.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PaletteFormat {
+    Pal, // Raw SNES colors, in BGR555.
+    Tpl, // Tile Layer Pro.
+    Act, // Adobe Color Table.
+    Gpl, // GIMP palette.
+    Png, // A swatch of 16 colors by row.
+}
+
+impl PaletteFormat {
+    pub const ALL: [PaletteFormat; 5] = [
+        PaletteFormat::Pal,
+        PaletteFormat::Tpl,
+        PaletteFormat::Act,
+        PaletteFormat::Gpl,
+        PaletteFormat::Png,
+    ];
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            PaletteFormat::Pal => "pal",
+            PaletteFormat::Tpl => "tpl",
+            PaletteFormat::Act => "act",
+            PaletteFormat::Gpl => "gpl",
+            PaletteFormat::Png => "png",
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            PaletteFormat::Pal => "SNES palette (.pal)",
+            PaletteFormat::Tpl => "Tile Layer Pro (.tpl)",
+            PaletteFormat::Act => "Adobe Color Table (.act)",
+            PaletteFormat::Gpl => "GIMP palette (.gpl)",
+            PaletteFormat::Png => "PNG swatch (.png)",
+        }
+    }
+
+    pub fn from_file_name(file_name: &str) -> Option<Self> {
+        let extension = file_name.rsplit_once('.')?.1.to_lowercase();
+        Self::ALL
+            .into_iter()
+            .find(|format| format.extension() == extension)
+    }
+}
+
+/// The colors of the whole palette, or of a single sub palette, in order.
+pub fn palette_colors(palette: &Palette, sub_palette: Option<usize>) -> Vec<Rgb888> {
+    sub_palette_range(sub_palette)
+        .flat_map(|sub_palette| palette.sub_palettes[sub_palette].colors)
+        .map(|color| color.into())
+        .collect()
+}
+
+/// Replace the colors of the whole palette, or of a single sub palette, in order.
+/// Colors past the end of the range are ignored.
+pub fn set_palette_colors(palette: &mut Palette, sub_palette: Option<usize>, colors: &[Rgb888]) {
+    let slots = sub_palette_range(sub_palette)
+        .flat_map(|sub_palette| (0..COLORS_BY_SUB_PALETTE).map(move |index| (sub_palette, index)));
+    for ((sub_palette, index), color) in slots.zip(colors) {
+        palette.sub_palettes[sub_palette].colors[index] = (*color).into();
+    }
+}
+
+pub fn export_colors(colors: &[Rgb888], format: PaletteFormat) -> Result<Vec<u8>, String> {
+    Ok(match format {
+        PaletteFormat::Pal => bgr555_bytes(colors),
+        PaletteFormat::Tpl => [TPL_HEADER, &[TPL_SNES], &bgr555_bytes(colors)].concat(),
+        PaletteFormat::Act => {
+            let mut bytes: Vec<u8> = colors.iter().flat_map(|c| [c.r, c.g, c.b]).collect();
+            bytes.resize(ACT_COLORS * 3, 0);
+            bytes.extend((colors.len() as u16).to_be_bytes());
+            bytes.extend(u16::MAX.to_be_bytes()); // No transparent color.
+            bytes
+        }
+        PaletteFormat::Gpl => {
+            let mut text = format!(
+                "{}\nName: Zen SM\nColumns: {}\n#\n",
+                GPL_HEADER, COLORS_BY_SUB_PALETTE
+            );
+            for (index, color) in colors.iter().enumerate() {
+                text += &format!(
+                    "{:3} {:3} {:3}\tColor {}\n",
+                    color.r, color.g, color.b, index
+                );
+            }
+            text.into_bytes()
+        }
+        PaletteFormat::Png => {
+            let rows = (colors.len() + COLORS_BY_SUB_PALETTE - 1) / COLORS_BY_SUB_PALETTE;
+            let width = COLORS_BY_SUB_PALETTE as u32 * SWATCH_SIZE;
+            let image = RgbImage::from_fn(width, rows as u32 * SWATCH_SIZE, |x, y| {
+                let index =
+                    (x / SWATCH_SIZE) as usize + (y / SWATCH_SIZE) as usize * COLORS_BY_SUB_PALETTE;
+                colors
+                    .get(index)
+                    .map_or(Rgb([0, 0, 0]), |c| Rgb([c.r, c.g, c.b]))
+            });
+
+            let mut png = Cursor::new(Vec::new());
+            image
+                .write_to(&mut png, ImageOutputFormat::Png)
+                .map_err(|e| e.to_string())?;
+            png.into_inner()
+        }
+    })
+}
+
+/// Read the colors of a palette file, quantized to the 15 bits colors of the SNES.
+pub fn import_colors(bytes: &[u8], format: PaletteFormat) -> Result<Vec<Rgb888>, String> {
+    let colors = match format {
+        PaletteFormat::Pal => from_bgr555_bytes(bytes),
+        PaletteFormat::Tpl => {
+            let Some(body) = bytes.strip_prefix(TPL_HEADER) else {
+                return Err("Not a Tile Layer Pro palette".to_string());
+            };
+            match body.split_first() {
+                Some((&TPL_RGB, colors)) => from_rgb_bytes(colors),
+                Some((&TPL_SNES, colors)) => from_bgr555_bytes(colors),
+                _ => return Err("Unsupported Tile Layer Pro palette type".to_string()),
+            }
+        }
+        PaletteFormat::Act => {
+            if bytes.len() < ACT_COLORS * 3 {
+                return Err("Adobe Color Table is too short".to_string());
+            }
+            let count = match bytes.get(ACT_COLORS * 3..ACT_COLORS * 3 + 2) {
+                Some(count) => (u16::from_be_bytes([count[0], count[1]]) as usize).min(ACT_COLORS),
+                None => ACT_COLORS,
+            };
+            from_rgb_bytes(&bytes[..count * 3])
+        }
+        PaletteFormat::Gpl => {
+            let text = std::str::from_utf8(bytes).map_err(|e| e.to_string())?;
+            if !text.starts_with(GPL_HEADER) {
+                return Err("Not a GIMP palette".to_string());
+            }
+            text.lines()
+                .skip(1)
+                .filter_map(|line| {
+                    let mut channels = line.split_whitespace().map(|c| c.parse::<u8>());
+                    match (channels.next(), channels.next(), channels.next()) {
+                        (Some(Ok(r)), Some(Ok(g)), Some(Ok(b))) => Some(Rgb888 { r, g, b }),
+                        _ => None, // Headers and comments.
+                    }
+                })
+                .collect()
+        }
+        PaletteFormat::Png => {
+            let image = image::load_from_memory(bytes)
+                .map_err(|e| e.to_string())?
+                .to_rgb8();
+            let swatch_size = image.width() / COLORS_BY_SUB_PALETTE as u32;
+            if swatch_size == 0 {
+                return Err("PNG swatch is too narrow".to_string());
+            }
+
+            // Sample the center of every swatch, row by row.
+            let rows = image.height() / swatch_size;
+            (0..rows * COLORS_BY_SUB_PALETTE as u32)
+                .map(|index| {
+                    let x = (index % COLORS_BY_SUB_PALETTE as u32) * swatch_size + swatch_size / 2;
+                    let y = (index / COLORS_BY_SUB_PALETTE as u32) * swatch_size + swatch_size / 2;
+                    let Rgb([r, g, b]) = *image.get_pixel(x, y);
+                    Rgb888 { r, g, b }
+                })
+                .collect()
+        }
+    };
+
+    Ok(colors.into_iter().map(quantize).collect())
+}
+
+/// Round a color to the nearest one the SNES can show.
+pub fn quantize(color: Rgb888) -> Rgb888 {
+    from_bgr555(to_bgr555(color))
+}
+
+fn sub_palette_range(sub_palette: Option<usize>) -> std::ops::Range<usize> {
+    match sub_palette {
+        Some(sub_palette) => sub_palette..sub_palette + 1,
+        None => 0..NUMBER_OF_SUB_PALETTES,
+    }
+}
+
+fn to_bgr555(color: Rgb888) -> u16 {
+    let channel = |value: u8| ((value as u16 + 4) >> 3).min(0x1F);
+    channel(color.r) | channel(color.g) << 5 | channel(color.b) << 10
+}
+
+fn from_bgr555(bgr555: u16) -> Rgb888 {
+    let channel = |shift: u16| {
+        let value = ((bgr555 >> shift) & 0x1F) as u8;
+        (value << 3) | (value >> 2)
+    };
+    Rgb888 {
+        r: channel(0),
+        g: channel(5),
+        b: channel(10),
+    }
+}
+
+fn bgr555_bytes(colors: &[Rgb888]) -> Vec<u8> {
+    colors
+        .iter()
+        .flat_map(|color| to_bgr555(*color).to_le_bytes())
+        .collect()
+}
+
+fn from_bgr555_bytes(bytes: &[u8]) -> Vec<Rgb888> {
+    bytes
+        .chunks_exact(2)
+        .map(|color| from_bgr555(u16::from_le_bytes([color[0], color[1]])))
+        .collect()
+}
+
+fn from_rgb_bytes(bytes: &[u8]) -> Vec<Rgb888> {
+    bytes
+        .chunks_exact(3)
+        .map(|color| Rgb888 {
+            r: color[0],
+            g: color[1],
+            b: color[2],
+        })
+        .collect()
+}
@@ -0,0 +1,507 @@
+use crate::rom::crc32;
+
+const IPS_HEADER: &[u8] = b"PATCH";
+const IPS_FOOTER: &[u8] = b"EOF";
+const IPS_EOF_OFFSET: usize = 0x454F46; // Reads as the footer, so no record can start there.
+const IPS_MAX_SIZE: usize = 0x1000000; // Offsets have 3 bytes.
+const IPS_MAX_RECORD: usize = 0xFFFF;
+
+const BPS_HEADER: &[u8] = b"BPS1";
+const BPS_FOOTER_SIZE: usize = 12; // CRC32 of the source, the target and the patch.
+
+// Commands of BPS actions.
+const SOURCE_READ: usize = 0;
+const TARGET_READ: usize = 1;
+const SOURCE_COPY: usize = 2;
+const TARGET_COPY: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PatchFormat {
+    Ips, // Records of bytes to write, with a truncation record when the ROM shrinks.
+    Bps, // Copy actions, checked by the CRC32 of the source and target ROMs.
+}
+
+impl PatchFormat {
+    pub const ALL: [PatchFormat; 2] = [PatchFormat::Ips, PatchFormat::Bps];
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            PatchFormat::Ips => "ips",
+            PatchFormat::Bps => "bps",
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            PatchFormat::Ips => "IPS patch (.ips)",
+            PatchFormat::Bps => "BPS patch (.bps)",
+        }
+    }
+
+    pub fn from_file_name(file_name: &str) -> Option<Self> {
+        let extension = file_name.rsplit_once('.')?.1.to_lowercase();
+        Self::ALL
+            .into_iter()
+            .find(|format| format.extension() == extension)
+    }
+}
+
+/// A patch turning `source` into `target`.
+pub fn create_patch(format: PatchFormat, source: &[u8], target: &[u8]) -> Result<Vec<u8>, String> {
+    match format {
+        PatchFormat::Ips => create_ips(source, target),
+        PatchFormat::Bps => Ok(create_bps(source, target)),
+    }
+}
+
+pub fn apply_patch(format: PatchFormat, source: &[u8], patch: &[u8]) -> Result<Vec<u8>, String> {
+    match format {
+        PatchFormat::Ips => apply_ips(source, patch),
+        PatchFormat::Bps => apply_bps(source, patch),
+    }
+}
+
+fn create_ips(source: &[u8], target: &[u8]) -> Result<Vec<u8>, String> {
+    if target.len() > IPS_MAX_SIZE {
+        return Err("The ROM is too large for an IPS patch".to_string());
+    }
+
+    let changed = |offset: usize| source.get(offset) != Some(&target[offset]);
+    let mut patch = IPS_HEADER.to_vec();
+    let mut offset = 0;
+    while offset < target.len() {
+        if !changed(offset) {
+            offset += 1;
+            continue;
+        }
+
+        // Start a byte earlier, rewriting it, rather than at the offset read as the footer.
+        let start = if offset == IPS_EOF_OFFSET {
+            offset - 1
+        } else {
+            offset
+        };
+        let mut end = offset;
+        while end < target.len() && end - start < IPS_MAX_RECORD && changed(end) {
+            end += 1;
+        }
+
+        patch.extend_from_slice(&(start as u32).to_be_bytes()[1..]);
+        patch.extend_from_slice(&((end - start) as u16).to_be_bytes());
+        patch.extend_from_slice(&target[start..end]);
+        offset = end;
+    }
+
+    patch.extend_from_slice(IPS_FOOTER);
+    if target.len() < source.len() {
+        patch.extend_from_slice(&(target.len() as u32).to_be_bytes()[1..]);
+    }
+    Ok(patch)
+}
+
+fn apply_ips(source: &[u8], patch: &[u8]) -> Result<Vec<u8>, String> {
+    let Some(mut data) = patch.strip_prefix(IPS_HEADER) else {
+        return Err("Not an IPS patch".to_string());
+    };
+
+    let mut target = source.to_vec();
+    loop {
+        let (offset, rest) = take(data, 3)?;
+        if offset == IPS_FOOTER {
+            // A truncation record may follow the footer.
+            if let Ok((size, _)) = take(rest, 3) {
+                target.truncate(be_number(size));
+            }
+            return Ok(target);
+        }
+
+        let offset = be_number(offset);
+        let (size, rest) = take(rest, 2)?;
+        let (bytes, rest) = match be_number(size) {
+            // Run-length encoded record, a byte repeated.
+            0 => {
+                let (run, rest) = take(rest, 3)?;
+                (vec![run[2]; be_number(&run[..2])], rest)
+            }
+            size => {
+                let (bytes, rest) = take(rest, size)?;
+                (bytes.to_vec(), rest)
+            }
+        };
+
+        let end = offset + bytes.len();
+        if target.len() < end {
+            target.resize(end, 0);
+        }
+        target[offset..end].copy_from_slice(&bytes);
+        data = rest;
+    }
+}
+
+/// Only reads unchanged bytes from the source, which is enough for ROM hacks edited in place.
+fn create_bps(source: &[u8], target: &[u8]) -> Vec<u8> {
+    let mut patch = BPS_HEADER.to_vec();
+    write_number(&mut patch, source.len());
+    write_number(&mut patch, target.len());
+    write_number(&mut patch, 0); // No metadata.
+
+    let unchanged = |offset: usize| source.get(offset) == Some(&target[offset]);
+    let mut offset = 0;
+    while offset < target.len() {
+        let start = offset;
+        let read_source = unchanged(offset);
+        while offset < target.len() && unchanged(offset) == read_source {
+            offset += 1;
+        }
+
+        let command = if read_source {
+            SOURCE_READ
+        } else {
+            TARGET_READ
+        };
+        write_number(&mut patch, (offset - start - 1) << 2 | command);
+        if !read_source {
+            patch.extend_from_slice(&target[start..offset]);
+        }
+    }
+
+    patch.extend_from_slice(&crc32(source).to_le_bytes());
+    patch.extend_from_slice(&crc32(target).to_le_bytes());
+    let patch_crc32 = crc32(&patch);
+    patch.extend_from_slice(&patch_crc32.to_le_bytes());
+    patch
+}
+
+fn apply_bps(source: &[u8], patch: &[u8]) -> Result<Vec<u8>, String> {
+    if !patch.starts_with(BPS_HEADER) {
+        return Err("Not a BPS patch".to_string());
+    }
+    if patch.len() < BPS_HEADER.len() + BPS_FOOTER_SIZE {
+        return Err("Truncated patch".to_string());
+    }
+
+    let (body, footer) = patch.split_at(patch.len() - BPS_FOOTER_SIZE);
+    let footer_crc32 = |index: usize| {
+        let bytes = &footer[index * 4..index * 4 + 4];
+        u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+    };
+    if crc32(&patch[..patch.len() - 4]) != footer_crc32(2) {
+        return Err("The patch is corrupted".to_string());
+    }
+    if crc32(source) != footer_crc32(0) {
+        return Err("The patch was made for another ROM".to_string());
+    }
+
+    let mut reader = &body[BPS_HEADER.len()..];
+    let source_size = read_number(&mut reader)?;
+    let target_size = read_number(&mut reader)?;
+    let metadata_size = read_number(&mut reader)?;
+    let (_, rest) = take(reader, metadata_size)?;
+    reader = rest;
+    if source_size != source.len() {
+        return Err("The patch was made for another ROM".to_string());
+    }
+
+    let invalid = || "Invalid patch".to_string();
+    // The size is read from the patch, so only trust it as far as the files go.
+    let mut target = Vec::with_capacity(target_size.min(source.len().max(patch.len())));
+    let mut source_offset = 0usize;
+    let mut target_offset = 0usize;
+    while !reader.is_empty() {
+        let action = read_number(&mut reader)?;
+        let length = (action >> 2) + 1;
+        // Also keeps target copies, which may repeat their own bytes, from growing without end.
+        if length > target_size - target.len() {
+            return Err(invalid());
+        }
+        match action & 0b11 {
+            SOURCE_READ => {
+                let start = target.len();
+                let end = start.checked_add(length).ok_or_else(invalid)?;
+                let bytes = source.get(start..end).ok_or_else(invalid)?;
+                target.extend_from_slice(bytes);
+            }
+            TARGET_READ => {
+                let (bytes, rest) = take(reader, length)?;
+                target.extend_from_slice(bytes);
+                reader = rest;
+            }
+            SOURCE_COPY => {
+                source_offset = relative_offset(source_offset, &mut reader)?;
+                let end = source_offset.checked_add(length).ok_or_else(invalid)?;
+                let bytes = source.get(source_offset..end).ok_or_else(invalid)?;
+                target.extend_from_slice(bytes);
+                source_offset = end;
+            }
+            TARGET_COPY => {
+                target_offset = relative_offset(target_offset, &mut reader)?;
+                // Byte by byte, as the copy can overlap the bytes it writes.
+                for _ in 0..length {
+                    let byte = *target.get(target_offset).ok_or_else(invalid)?;
+                    target.push(byte);
+                    target_offset += 1;
+                }
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    if target.len() != target_size || crc32(&target) != footer_crc32(1) {
+        return Err("The patched ROM doesn't match the patch".to_string());
+    }
+    Ok(target)
+}
+
+fn take(data: &[u8], size: usize) -> Result<(&[u8], &[u8]), String> {
+    if data.len() < size {
+        return Err("Truncated patch".to_string());
+    }
+    Ok(data.split_at(size))
+}
+
+fn be_number(bytes: &[u8]) -> usize {
+    bytes
+        .iter()
+        .fold(0, |number, byte| number << 8 | *byte as usize)
+}
+
+/// Variable length number of BPS patches, 7 bits by byte with the last byte flagged.
+fn write_number(patch: &mut Vec<u8>, mut number: usize) {
+    loop {
+        let bits = (number & 0x7F) as u8;
+        number >>= 7;
+        if number == 0 {
+            patch.push(0x80 | bits);
+            return;
+        }
+        patch.push(bits);
+        number -= 1;
+    }
+}
+
+fn read_number(reader: &mut &[u8]) -> Result<usize, String> {
+    let invalid = || "Invalid patch".to_string();
+    let mut number = 0usize;
+    let mut shift = 1usize;
+    loop {
+        let Some((byte, rest)) = reader.split_first() else {
+            return Err("Truncated patch".to_string());
+        };
+        *reader = rest;
+
+        let bits = ((*byte & 0x7F) as usize)
+            .checked_mul(shift)
+            .ok_or_else(invalid)?;
+        number = number.checked_add(bits).ok_or_else(invalid)?;
+        if *byte & 0x80 != 0 {
+            return Ok(number);
+        }
+        shift = shift.checked_mul(0x80).ok_or_else(invalid)?;
+        number = number.checked_add(shift).ok_or_else(invalid)?;
+    }
+}
+
+/// Move an offset of copy actions by the signed number that follows.
+fn relative_offset(offset: usize, reader: &mut &[u8]) -> Result<usize, String> {
+    let number = read_number(reader)?;
+    let distance = (number >> 1) as isize;
+    let distance = if number & 1 == 1 { -distance } else { distance };
+    offset
+        .checked_add_signed(distance)
+        .ok_or_else(|| "Invalid patch".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(format: PatchFormat, source: &[u8], target: &[u8]) {
+        let patch = create_patch(format, source, target).unwrap();
+        assert_eq!(apply_patch(format, source, &patch).unwrap(), target);
+    }
+
+    // A source with some variety, so unchanged bytes aren't all the same.
+    fn source(size: usize) -> Vec<u8> {
+        (0..size).map(|index| (index * 7 % 251) as u8).collect()
+    }
+
+    #[test]
+    fn round_trip_edits() {
+        let source = source(0x1000);
+        let mut target = source.clone();
+        target[0] ^= 0xFF;
+        target[0x10..0x20].fill(0xAA);
+        target[0xFFF] ^= 0xFF;
+
+        for format in PatchFormat::ALL {
+            round_trip(format, &source, &target);
+            round_trip(format, &source, &source);
+        }
+    }
+
+    #[test]
+    fn round_trip_growing_and_shrinking() {
+        let source = source(0x1000);
+        let mut grown = source.clone();
+        grown.extend_from_slice(&[0, 1, 2, 0, 0]);
+        let mut shrunk = source[..0x800].to_vec();
+        shrunk[0x400] ^= 0xFF;
+
+        for format in PatchFormat::ALL {
+            round_trip(format, &source, &grown);
+            round_trip(format, &source, &shrunk);
+            round_trip(format, &source, &source[..0x800]);
+        }
+    }
+
+    #[test]
+    fn ips_truncation_record() {
+        let source = source(0x100);
+        let patch = create_patch(PatchFormat::Ips, &source, &source[..0x80]).unwrap();
+        assert_eq!(
+            patch,
+            [b"PATCHEOF".as_slice(), &[0x00, 0x00, 0x80]].concat()
+        );
+    }
+
+    #[test]
+    fn ips_record_at_eof_offset() {
+        let source = source(IPS_EOF_OFFSET + 0x10);
+        let mut target = source.clone();
+        target[IPS_EOF_OFFSET] ^= 0xFF;
+        target[IPS_EOF_OFFSET + 1] ^= 0xFF;
+
+        let patch = create_patch(PatchFormat::Ips, &source, &target).unwrap();
+        // The record starts a byte earlier, as its offset would read as the footer.
+        assert_eq!(&patch[5..10], &[0x45, 0x4F, 0x45, 0x00, 0x03]);
+        assert_eq!(
+            apply_patch(PatchFormat::Ips, &source, &patch).unwrap(),
+            target
+        );
+    }
+
+    #[test]
+    fn ips_long_record_split_before_eof_offset() {
+        // The record is split at its maximum size, right where the next one would start
+        // at the offset read as the footer.
+        let start = IPS_EOF_OFFSET - IPS_MAX_RECORD;
+        let source = source(IPS_EOF_OFFSET + 0x10);
+        let mut target = source.clone();
+        for byte in &mut target[start..IPS_EOF_OFFSET + 0x8] {
+            *byte ^= 0xFF;
+        }
+
+        round_trip(PatchFormat::Ips, &source, &target);
+    }
+
+    #[test]
+    fn ips_rle_records() {
+        let source = source(0x20);
+        let patch = [
+            b"PATCH".as_slice(),
+            &[0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x08, 0xEE], // 8 times 0xEE at 0x04.
+            &[0x00, 0x00, 0x1E, 0x00, 0x00, 0x00, 0x04, 0x11], // Grows the ROM by 2 bytes.
+            b"EOF",
+        ]
+        .concat();
+
+        let mut target = source.clone();
+        target[0x04..0x0C].fill(0xEE);
+        target.truncate(0x1E);
+        target.extend_from_slice(&[0x11; 4]);
+        assert_eq!(
+            apply_patch(PatchFormat::Ips, &source, &patch).unwrap(),
+            target
+        );
+    }
+
+    #[test]
+    fn ips_truncated_patch() {
+        let source = source(0x100);
+        let mut target = source.clone();
+        target[0x10] ^= 0xFF;
+        let patch = create_patch(PatchFormat::Ips, &source, &target).unwrap();
+
+        assert!(apply_patch(PatchFormat::Ips, &source, &patch[..patch.len() - 3]).is_err());
+        assert!(apply_patch(PatchFormat::Ips, &source, b"NOT A PATCH").is_err());
+    }
+
+    #[test]
+    fn bps_corrupted_footer() {
+        let source = source(0x100);
+        let mut target = source.clone();
+        target[0x10] ^= 0xFF;
+        let patch = create_patch(PatchFormat::Bps, &source, &target).unwrap();
+
+        for index in patch.len() - BPS_FOOTER_SIZE..patch.len() {
+            let mut corrupted = patch.clone();
+            corrupted[index] ^= 0x01;
+            assert_eq!(
+                apply_patch(PatchFormat::Bps, &source, &corrupted),
+                Err("The patch is corrupted".to_string())
+            );
+        }
+    }
+
+    #[test]
+    fn bps_other_source() {
+        let source = source(0x100);
+        let mut target = source.clone();
+        target[0x10] ^= 0xFF;
+        let patch = create_patch(PatchFormat::Bps, &source, &target).unwrap();
+
+        assert_eq!(
+            apply_patch(PatchFormat::Bps, &target, &patch),
+            Err("The patch was made for another ROM".to_string())
+        );
+    }
+
+    // A BPS patch with valid CRC32s, from the actions given.
+    fn bps_patch(source: &[u8], target_size: usize, actions: &[usize]) -> Vec<u8> {
+        let mut patch = BPS_HEADER.to_vec();
+        write_number(&mut patch, source.len());
+        write_number(&mut patch, target_size);
+        write_number(&mut patch, 0);
+        for action in actions {
+            write_number(&mut patch, *action);
+        }
+        patch.extend_from_slice(&crc32(source).to_le_bytes());
+        patch.extend_from_slice(&0u32.to_le_bytes());
+        let patch_crc32 = crc32(&patch);
+        patch.extend_from_slice(&patch_crc32.to_le_bytes());
+        patch
+    }
+
+    #[test]
+    fn bps_huge_sizes() {
+        let source = source(0x100);
+
+        // Doesn't allocate the target size read from the patch.
+        let patch = bps_patch(&source, usize::MAX, &[]);
+        assert!(apply_patch(PatchFormat::Bps, &source, &patch).is_err());
+
+        // Copies past the target size, that would otherwise grow the target without end.
+        let patch = bps_patch(
+            &source,
+            0x10,
+            &[SOURCE_READ, (usize::MAX >> 2) << 2 | TARGET_COPY, 0],
+        );
+        assert!(apply_patch(PatchFormat::Bps, &source, &patch).is_err());
+
+        // Copies from far past the end of the source.
+        let far = (isize::MAX as usize) << 1;
+        let patch = bps_patch(&source, usize::MAX, &[(0x7F << 2) | SOURCE_COPY, far]);
+        assert!(apply_patch(PatchFormat::Bps, &source, &patch).is_err());
+    }
+
+    #[test]
+    fn bps_numbers() {
+        for number in [0, 1, 0x7F, 0x80, 0x407F, 0x4080, 0x123456, usize::MAX >> 8] {
+            let mut bytes = Vec::new();
+            write_number(&mut bytes, number);
+            let mut reader = bytes.as_slice();
+            assert_eq!(read_number(&mut reader), Ok(number));
+            assert!(reader.is_empty());
+        }
+    }
+}
@@ -0,0 +1,685 @@
+use std::collections::{HashSet, VecDeque};
+
+use zen::{
+    graphics::{
+        gfx::{Gfx, GFX_TILE_WIDTH},
+        Palette,
+    },
+    super_metroid::{
+        level_data::{Block, BtsBlock, LevelData},
+        tileset::Tileset,
+        SuperMetroid,
+    },
+};
+
+use crate::{
+    room::{Door, EnemyGfx, EnemyPopulation, Plm, RoomData, RoomHeader},
+    widgets::{write_block_entries, Layer, TileEntry},
+};
+
+const HISTORY_CAPACITY: usize = 256;
+
+/// A single block change in a level, with the values before and after the edit.
+#[derive(Debug, Clone, Copy)]
+pub struct BlockEdit {
+    pub index: usize,
+    pub old: (Block, BtsBlock),
+    pub new: (Block, BtsBlock),
+}
+
+/// A single pixel change in a graphics sheet, with the color indexes before and after the edit.
+#[derive(Debug, Clone, Copy)]
+pub struct PixelEdit {
+    pub position: [usize; 2], // In pixels, in the graphics sheet.
+    pub old: u8,
+    pub new: u8,
+}
+
+/// A level replaced as a whole, like when resizing its room.
+pub struct LevelReplacement {
+    pub level_address: usize,
+    pub old: LevelData,
+    pub new: LevelData,
+}
+
+/// Scroll data replaced as a whole, like when resizing its room.
+pub struct ScrollsReplacement {
+    pub address: usize,
+    pub old: Vec<u8>,
+    pub new: Vec<u8>,
+}
+
+/// What an edit of a list, like the enemies of a state, changed.
+/// Consecutive changes of the same value are merged into a single operation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListEdit {
+    Added,
+    Removed,
+    Moved(usize),
+    Values(usize), // Index of the entry whose values changed.
+    Count,         // A count stored with the list, like the enemies to kill.
+}
+
+impl ListEdit {
+    pub fn is_continuous(&self) -> bool {
+        matches!(self, ListEdit::Values(_) | ListEdit::Count)
+    }
+}
+
+/// A reversible mutation of `SuperMetroid`.
+pub enum Operation {
+    Level {
+        level_address: usize,
+        layer: Layer,
+        edits: Vec<BlockEdit>,
+    },
+    Palette {
+        palette_address: usize,
+        color: Option<[usize; 2]>, // Sub palette and color index being edited, if a single one.
+        old: Palette,
+        new: Palette,
+    },
+    Tileset {
+        index: usize,
+        old: Tileset,
+        new: Tileset,
+    },
+    Graphics {
+        graphics_address: usize,
+        stroke: usize, // Edits of the same stroke are merged.
+        edits: Vec<PixelEdit>,
+    },
+    TileTable {
+        tile_table_address: usize,
+        block: usize,
+        corner: usize, // Edits of the same tile of a block are merged.
+        old: [TileEntry; 4],
+        new: [TileEntry; 4],
+    },
+    StateTileset {
+        state_address: usize,
+        old: u8,
+        new: u8,
+    },
+    Room {
+        room_address: usize,
+        old: RoomHeader,
+        new: RoomHeader,
+        levels: Vec<LevelReplacement>,
+        scrolls: Vec<ScrollsReplacement>,
+    },
+    Door {
+        address: usize,
+        old: Door,
+        new: Door,
+    },
+    EnemyPopulation {
+        address: usize,
+        edit: ListEdit,
+        old: EnemyPopulation,
+        new: EnemyPopulation,
+    },
+    EnemySet {
+        address: usize,
+        edit: ListEdit,
+        old: Vec<EnemyGfx>,
+        new: Vec<EnemyGfx>,
+    },
+    PlmSet {
+        address: usize,
+        edit: ListEdit,
+        old: Vec<Plm>,
+        new: Vec<Plm>,
+    },
+    Scrolls {
+        address: usize,
+        old: Vec<u8>,
+        new: Vec<u8>,
+    },
+}
+
+/// What an undone or redone operation touched, so the caller can re-sync its textures.
+#[derive(Debug, Clone, Copy)]
+pub enum Affected {
+    Level { level_address: usize },
+    Palette { palette_address: usize },
+    Tileset,
+    Graphics { graphics_address: usize },
+    TileTable { tile_table_address: usize },
+    Room,
+    Doors,
+    Enemies,
+    Plms,
+    Scrolls,
+}
+
+impl Operation {
+    fn affected(&self) -> Affected {
+        match self {
+            Operation::Level { level_address, .. } => Affected::Level {
+                level_address: *level_address,
+            },
+            Operation::Palette {
+                palette_address, ..
+            } => Affected::Palette {
+                palette_address: *palette_address,
+            },
+            Operation::Tileset { .. } | Operation::StateTileset { .. } => Affected::Tileset,
+            Operation::Graphics {
+                graphics_address, ..
+            } => Affected::Graphics {
+                graphics_address: *graphics_address,
+            },
+            Operation::TileTable {
+                tile_table_address, ..
+            } => Affected::TileTable {
+                tile_table_address: *tile_table_address,
+            },
+            Operation::Room { .. } => Affected::Room,
+            Operation::Door { .. } => Affected::Doors,
+            Operation::EnemyPopulation { .. } | Operation::EnemySet { .. } => Affected::Enemies,
+            Operation::PlmSet { .. } => Affected::Plms,
+            Operation::Scrolls { .. } => Affected::Scrolls,
+        }
+    }
+
+    fn undo(&self, sm: &mut SuperMetroid, room_data: &mut RoomData) {
+        match self {
+            Operation::Level {
+                level_address,
+                layer,
+                edits,
+            } => {
+                let Some(level) = sm.levels.get_mut(level_address) else {return};
+                for edit in edits.iter().rev() {
+                    write_block(level, *layer, edit.index, edit.old);
+                }
+            }
+            Operation::Palette {
+                palette_address,
+                old,
+                ..
+            } => {
+                sm.palettes.insert(*palette_address, *old);
+            }
+            Operation::Tileset { index, old, .. } => sm.tilesets[*index] = *old,
+            Operation::Graphics {
+                graphics_address,
+                edits,
+                ..
+            } => {
+                let Some(graphics) = sm.graphics.get_mut(graphics_address) else {return};
+                for edit in edits.iter().rev() {
+                    write_pixel(graphics, edit.position, edit.old);
+                }
+            }
+            Operation::TileTable {
+                tile_table_address,
+                block,
+                old,
+                ..
+            } => {
+                let Some(tile_table) = sm.tile_tables.get_mut(tile_table_address) else {return};
+                write_block_entries(tile_table, *block, old);
+            }
+            Operation::StateTileset {
+                state_address, old, ..
+            } => {
+                if let Some(state) = sm.states.get_mut(state_address) {
+                    state.tileset = *old;
+                }
+            }
+            Operation::Room {
+                room_address,
+                old,
+                levels,
+                scrolls,
+                ..
+            } => {
+                if let Some(room) = sm.rooms.get_mut(room_address) {
+                    old.write(room);
+                }
+                for level in levels {
+                    sm.levels.insert(level.level_address, level.old.clone());
+                }
+                for replacement in scrolls {
+                    room_data
+                        .scrolls
+                        .insert(replacement.address, replacement.old.clone());
+                }
+            }
+            Operation::Door { address, old, .. } => {
+                room_data.doors.insert(*address, *old);
+            }
+            Operation::EnemyPopulation { address, old, .. } => {
+                room_data.enemy_populations.insert(*address, old.clone());
+            }
+            Operation::EnemySet { address, old, .. } => {
+                room_data.enemy_sets.insert(*address, old.clone());
+            }
+            Operation::PlmSet { address, old, .. } => {
+                room_data.plm_sets.insert(*address, old.clone());
+            }
+            Operation::Scrolls { address, old, .. } => {
+                room_data.scrolls.insert(*address, old.clone());
+            }
+        }
+    }
+
+    fn redo(&self, sm: &mut SuperMetroid, room_data: &mut RoomData) {
+        match self {
+            Operation::Level {
+                level_address,
+                layer,
+                edits,
+            } => {
+                let Some(level) = sm.levels.get_mut(level_address) else {return};
+                for edit in edits.iter() {
+                    write_block(level, *layer, edit.index, edit.new);
+                }
+            }
+            Operation::Palette {
+                palette_address,
+                new,
+                ..
+            } => {
+                sm.palettes.insert(*palette_address, *new);
+            }
+            Operation::Tileset { index, new, .. } => sm.tilesets[*index] = *new,
+            Operation::Graphics {
+                graphics_address,
+                edits,
+                ..
+            } => {
+                let Some(graphics) = sm.graphics.get_mut(graphics_address) else {return};
+                for edit in edits.iter() {
+                    write_pixel(graphics, edit.position, edit.new);
+                }
+            }
+            Operation::TileTable {
+                tile_table_address,
+                block,
+                new,
+                ..
+            } => {
+                let Some(tile_table) = sm.tile_tables.get_mut(tile_table_address) else {return};
+                write_block_entries(tile_table, *block, new);
+            }
+            Operation::StateTileset {
+                state_address, new, ..
+            } => {
+                if let Some(state) = sm.states.get_mut(state_address) {
+                    state.tileset = *new;
+                }
+            }
+            Operation::Room {
+                room_address,
+                new,
+                levels,
+                scrolls,
+                ..
+            } => {
+                if let Some(room) = sm.rooms.get_mut(room_address) {
+                    new.write(room);
+                }
+                for level in levels {
+                    sm.levels.insert(level.level_address, level.new.clone());
+                }
+                for replacement in scrolls {
+                    room_data
+                        .scrolls
+                        .insert(replacement.address, replacement.new.clone());
+                }
+            }
+            Operation::Door { address, new, .. } => {
+                room_data.doors.insert(*address, *new);
+            }
+            Operation::EnemyPopulation { address, new, .. } => {
+                room_data.enemy_populations.insert(*address, new.clone());
+            }
+            Operation::EnemySet { address, new, .. } => {
+                room_data.enemy_sets.insert(*address, new.clone());
+            }
+            Operation::PlmSet { address, new, .. } => {
+                room_data.plm_sets.insert(*address, new.clone());
+            }
+            Operation::Scrolls { address, new, .. } => {
+                room_data.scrolls.insert(*address, new.clone());
+            }
+        }
+    }
+
+    /// Merge `other` into this operation when both are part of the same continuous edit,
+    /// like dragging the color picker over a single palette color, a pencil stroke,
+    /// a tile of a block, or a door or enemy value.
+    fn merge(&mut self, other: &Operation) -> bool {
+        match (self, other) {
+            (
+                Operation::Palette {
+                    palette_address,
+                    color,
+                    new,
+                    ..
+                },
+                Operation::Palette {
+                    palette_address: other_address,
+                    color: other_color,
+                    new: other_new,
+                    ..
+                },
+            ) if palette_address == other_address && color.is_some() && color == other_color => {
+                *new = *other_new;
+                true
+            }
+            (
+                Operation::Graphics {
+                    graphics_address,
+                    stroke,
+                    edits,
+                },
+                Operation::Graphics {
+                    graphics_address: other_address,
+                    stroke: other_stroke,
+                    edits: other_edits,
+                },
+            ) if graphics_address == other_address && stroke == other_stroke => {
+                edits.extend(other_edits.iter().copied());
+                true
+            }
+            (
+                Operation::TileTable {
+                    tile_table_address,
+                    block,
+                    corner,
+                    new,
+                    ..
+                },
+                Operation::TileTable {
+                    tile_table_address: other_address,
+                    block: other_block,
+                    corner: other_corner,
+                    new: other_new,
+                    ..
+                },
+            ) if tile_table_address == other_address
+                && block == other_block
+                && corner == other_corner =>
+            {
+                *new = *other_new;
+                true
+            }
+            (
+                Operation::Door { address, new, .. },
+                Operation::Door {
+                    address: other_address,
+                    new: other_new,
+                    ..
+                },
+            ) if address == other_address => {
+                *new = *other_new;
+                true
+            }
+            (
+                Operation::EnemyPopulation {
+                    address, edit, new, ..
+                },
+                Operation::EnemyPopulation {
+                    address: other_address,
+                    edit: other_edit,
+                    new: other_new,
+                    ..
+                },
+            ) if address == other_address && edit == other_edit && edit.is_continuous() => {
+                *new = other_new.clone();
+                true
+            }
+            (
+                Operation::EnemySet {
+                    address, edit, new, ..
+                },
+                Operation::EnemySet {
+                    address: other_address,
+                    edit: other_edit,
+                    new: other_new,
+                    ..
+                },
+            ) if address == other_address && edit == other_edit && edit.is_continuous() => {
+                *new = other_new.clone();
+                true
+            }
+            (
+                Operation::PlmSet {
+                    address, edit, new, ..
+                },
+                Operation::PlmSet {
+                    address: other_address,
+                    edit: other_edit,
+                    new: other_new,
+                    ..
+                },
+            ) if address == other_address && edit == other_edit && edit.is_continuous() => {
+                *new = other_new.clone();
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+pub fn write_block(
+    level: &mut LevelData,
+    layer: Layer,
+    index: usize,
+    (block, bts): (Block, BtsBlock),
+) {
+    match layer {
+        Layer::Layer1 => {
+            level.layer1[index] = block;
+            level.bts[index] = bts;
+        }
+        Layer::Layer2 => level.layer2[index] = block,
+    }
+}
+
+/// Write a pixel of a graphics sheet, where tiles are laid out row by row.
+pub fn write_pixel(graphics: &mut Gfx, [x, y]: [usize; 2], color: u8) {
+    let tiles_by_row = graphics.size()[0] / GFX_TILE_WIDTH;
+    let tile = (x / GFX_TILE_WIDTH) + (y / GFX_TILE_WIDTH) * tiles_by_row;
+    let pixel = (x % GFX_TILE_WIDTH) + (y % GFX_TILE_WIDTH) * GFX_TILE_WIDTH;
+    graphics.tiles[tile].colors[pixel] = color;
+}
+
+/// What was edited since the ROM was loaded or last saved.
+#[derive(Default)]
+pub struct Modified {
+    levels: HashSet<usize>,    // By level address.
+    palettes: HashSet<usize>,  // By palette address.
+    tilesets: bool,            // The tileset table, or a tile table.
+    graphics: HashSet<usize>,  // By graphics address.
+    states: HashSet<usize>,    // Tileset of a state, by state address.
+    rooms: HashSet<usize>,     // Room headers, by room address.
+    room_data: HashSet<usize>, // Doors, enemies, PLMs and scrolls, by address.
+}
+
+impl Modified {
+    fn mark(&mut self, operation: &Operation) {
+        match operation {
+            Operation::Level { level_address, .. } => {
+                self.levels.insert(*level_address);
+            }
+            Operation::Palette {
+                palette_address, ..
+            } => {
+                self.palettes.insert(*palette_address);
+            }
+            Operation::Tileset { .. } | Operation::TileTable { .. } => self.tilesets = true,
+            Operation::Graphics {
+                graphics_address, ..
+            } => {
+                self.graphics.insert(*graphics_address);
+            }
+            Operation::StateTileset { state_address, .. } => {
+                self.states.insert(*state_address);
+            }
+            Operation::Room {
+                room_address,
+                levels,
+                scrolls,
+                ..
+            } => {
+                self.rooms.insert(*room_address);
+                self.levels
+                    .extend(levels.iter().map(|level| level.level_address));
+                self.room_data
+                    .extend(scrolls.iter().map(|scrolls| scrolls.address));
+            }
+            Operation::Door { address, .. }
+            | Operation::EnemyPopulation { address, .. }
+            | Operation::EnemySet { address, .. }
+            | Operation::PlmSet { address, .. }
+            | Operation::Scrolls { address, .. } => {
+                self.room_data.insert(*address);
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.subsystems().is_empty()
+    }
+
+    /// Names of the modified subsystems, for the user.
+    pub fn subsystems(&self) -> Vec<&'static str> {
+        [
+            ("levels", !self.levels.is_empty()),
+            ("palettes", !self.palettes.is_empty()),
+            ("tilesets", self.tilesets || !self.states.is_empty()),
+            ("graphics", !self.graphics.is_empty()),
+            (
+                "rooms",
+                !self.rooms.is_empty() || !self.room_data.is_empty(),
+            ),
+        ]
+        .into_iter()
+        .filter_map(|(name, modified)| modified.then_some(name))
+        .collect()
+    }
+
+    /// Whether the room, or the level or data of one of its states, was edited.
+    pub fn is_room_modified(
+        &self,
+        sm: &SuperMetroid,
+        room_data: &RoomData,
+        room_address: usize,
+    ) -> bool {
+        if self.rooms.contains(&room_address) {
+            return true;
+        }
+
+        let mut doors = room_data
+            .room_doors
+            .get(&room_address)
+            .into_iter()
+            .flatten();
+        if doors.any(|door| self.room_data.contains(door)) {
+            return true;
+        }
+
+        let Some(room) = sm.rooms.get(&room_address) else {return false};
+        room.state_conditions.iter().any(|state_condition| {
+            let state_address = state_condition.state_address as usize;
+            let Some(state) = sm.states.get(&state_address) else {return false};
+
+            let state_data = [
+                &room_data.state_enemy_populations,
+                &room_data.state_enemy_sets,
+                &room_data.state_plm_sets,
+                &room_data.state_scrolls,
+            ];
+            self.states.contains(&state_address)
+                || self.levels.contains(&(state.level_address as usize))
+                || state_data.iter().any(|addresses| {
+                    addresses
+                        .get(&state_address)
+                        .map_or(false, |address| self.room_data.contains(address))
+                })
+        })
+    }
+}
+
+#[derive(Default)]
+pub struct History {
+    undo_stack: VecDeque<Operation>,
+    redo_stack: Vec<Operation>,
+    modified: Modified,
+    // Whether the interaction that pushed the last operation, like a drag, is still going on.
+    // Operations are only merged within it.
+    merging: bool,
+}
+
+impl History {
+    pub fn push(&mut self, operation: Operation) {
+        self.redo_stack.clear();
+        self.modified.mark(&operation);
+
+        if let Some(last) = self.undo_stack.back_mut().filter(|_| self.merging) {
+            if last.merge(&operation) {
+                return;
+            }
+        }
+
+        if self.undo_stack.len() == HISTORY_CAPACITY {
+            self.undo_stack.pop_front();
+        }
+        self.undo_stack.push_back(operation);
+        self.merging = true;
+    }
+
+    /// The next operations are kept apart from the last one, even if they could be merged.
+    pub fn end_interaction(&mut self) {
+        self.merging = false;
+    }
+
+    pub fn undo(&mut self, sm: &mut SuperMetroid, room_data: &mut RoomData) -> Option<Affected> {
+        let operation = self.undo_stack.pop_back()?;
+        self.merging = false;
+        operation.undo(sm, room_data);
+        self.modified.mark(&operation);
+        let affected = operation.affected();
+        self.redo_stack.push(operation);
+        Some(affected)
+    }
+
+    pub fn redo(&mut self, sm: &mut SuperMetroid, room_data: &mut RoomData) -> Option<Affected> {
+        let operation = self.redo_stack.pop()?;
+        self.merging = false;
+        operation.redo(sm, room_data);
+        self.modified.mark(&operation);
+        let affected = operation.affected();
+        self.undo_stack.push_back(operation);
+        Some(affected)
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo_stack.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo_stack.is_empty()
+    }
+
+    pub fn modified(&self) -> &Modified {
+        &self.modified
+    }
+
+    /// Forget what was modified, once the ROM is saved. Operations can still be undone.
+    pub fn mark_saved(&mut self) {
+        self.modified = Modified::default();
+    }
+
+    pub fn clear(&mut self) {
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+        self.modified = Modified::default();
+        self.merging = false;
+    }
+}
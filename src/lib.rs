@@ -4,5 +4,14 @@
 mod app;
 pub mod assets;
 pub mod colors;
+pub mod export;
+pub mod formats;
+pub mod history;
+pub mod notifications;
+#[cfg(feature = "persistence")]
+pub mod project;
+pub mod recent_roms;
+pub mod rom;
+pub mod room;
 pub mod widgets;
 pub use app::ZenSM;
@@ -6,17 +6,92 @@
 fn main() {
     env_logger::init(); // Log to stderr (if you run with `RUST_LOG=debug`).
 
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("export") {
+        if let Err(e) = export_rooms(&args[1..]) {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
     _start_puffin_server();
 
-    let app = zen_sm::ZenSM::default();
+    // The ROM to open at startup, from `--rom <ROM>` or the environment.
+    let rom_path = match args.iter().position(|arg| arg == "--rom") {
+        Some(index) => match args.get(index + 1) {
+            Some(path) => Some(path.clone()),
+            None => {
+                eprintln!("{}", USAGE);
+                std::process::exit(1);
+            }
+        },
+        None => std::env::var(ROM_ENV_VAR).ok(),
+    };
+
     eframe::run_native(
         "Zen SM",
         eframe::NativeOptions::default(),
-        Box::new(|_cc| Box::new(app)),
+        Box::new(|cc| Box::new(zen_sm::ZenSM::new(cc, rom_path))),
     )
     .unwrap();
 }
 
+#[cfg(not(target_arch = "wasm32"))]
+const ROM_ENV_VAR: &str = "ZEN_SM_ROM";
+
+#[cfg(not(target_arch = "wasm32"))]
+const USAGE: &str = "Usage: zen_sm_bin [--rom <ROM>]";
+
+#[cfg(not(target_arch = "wasm32"))]
+const EXPORT_USAGE: &str = "Usage: zen_sm_bin export --rom <ROM> --out <DIR> [--bts] [--no-layer2]";
+
+/// Render every room of a ROM to PNG images, without opening a window.
+#[cfg(not(target_arch = "wasm32"))]
+fn export_rooms(args: &[String]) -> Result<(), String> {
+    use std::{
+        collections::HashMap,
+        path::Path,
+        sync::{Arc, Mutex},
+    };
+    use zen_sm::export::{self, RoomImageOptions};
+
+    let mut rom = None;
+    let mut out_dir = None;
+    let mut options = RoomImageOptions::default();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--rom" => rom = args.next(),
+            "--out" => out_dir = args.next(),
+            "--bts" => options.bts = true,
+            "--no-layer2" => options.layer2 = false,
+            _ => return Err(format!("Unknown argument: {}\n{}", arg, EXPORT_USAGE)),
+        }
+    }
+    let (Some(rom), Some(out_dir)) = (rom, out_dir) else {
+        return Err(EXPORT_USAGE.to_string());
+    };
+
+    let data = std::fs::read(rom).map_err(|e| format!("Failed to read {}: {}", rom, e))?;
+    let (_, data) = zen_sm::rom::split_copier_header(&data);
+    let sm = zen::super_metroid::load_unheadered_rom(data)
+        .map_err(|e| format!("Failed to load {}: {:?}", rom, e))?;
+
+    let bts_icons = Arc::new(Mutex::new(HashMap::new()));
+    if options.bts {
+        zen_sm::assets::read_bts_icons(&bts_icons)?;
+    }
+
+    let bts_icons = bts_icons.lock().unwrap();
+    let count = export::export_all_rooms(&sm, Path::new(out_dir), &bts_icons, options)
+        .map_err(|e| format!("Failed to export to {}: {}", out_dir, e))?;
+    println!("Exported {} room states to {}", count, out_dir);
+
+    Ok(())
+}
+
 #[cfg(not(target_arch = "wasm32"))]
 fn _start_puffin_server() {
     puffin::set_scopes_on(true); // tell puffin to collect data
@@ -47,13 +122,12 @@ fn main() {
 
     let web_options = eframe::WebOptions::default();
 
-    let app = zen_sm::ZenSM::default();
     wasm_bindgen_futures::spawn_local(async {
         eframe::WebRunner::new()
             .start(
                 "the_canvas_id", // hardcode it
                 web_options,
-                Box::new(|_cc| Box::new(app)),
+                Box::new(|cc| Box::new(zen_sm::ZenSM::new(cc, None))),
             )
             .await
             .expect("failed to start eframe");
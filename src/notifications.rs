@@ -0,0 +1,41 @@
+//! Messages for the user, reported from anywhere, including background threads,
+//! and shown by the app in toasts and its error log.
+
+use std::sync::Mutex;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    Info,
+    Warning,
+    Error,
+}
+
+lazy_static::lazy_static! {
+    static ref PENDING: Mutex<Vec<(Level, String)>> = Mutex::new(Vec::new());
+}
+
+pub fn info(message: impl Into<String>) {
+    notify(Level::Info, message.into());
+}
+
+pub fn warning(message: impl Into<String>) {
+    notify(Level::Warning, message.into());
+}
+
+pub fn error(message: impl Into<String>) {
+    notify(Level::Error, message.into());
+}
+
+/// Messages reported since the last call.
+pub fn take() -> Vec<(Level, String)> {
+    std::mem::take(&mut *PENDING.lock().unwrap())
+}
+
+fn notify(level: Level, message: String) {
+    match level {
+        Level::Info => log::info!("{}", message),
+        Level::Warning => log::warn!("{}", message),
+        Level::Error => log::error!("{}", message),
+    }
+    PENDING.lock().unwrap().push((level, message));
+}
@@ -0,0 +1,529 @@
+//! Project files, storing the edits of a hack apart from the ROM.
+//!
+//! A project records the CRC32 of the ROM it was made from, and everything that differs from it
+//! as structured data. Applying it to that same ROM rebuilds the hack, so a hack can be reviewed
+//! in git and shared without distributing the ROM.
+
+use std::collections::{BTreeMap, HashMap};
+
+use serde::{Deserialize, Serialize};
+use zen::{
+    graphics::{gfx::Gfx, Palette, Rgb888},
+    super_metroid::{
+        level_data::{Block, BlockType, LevelData},
+        tileset::Tileset,
+        SuperMetroid,
+    },
+};
+
+use crate::{
+    formats::palette::{palette_colors, set_palette_colors},
+    history::write_pixel,
+    rom::{self, pc_to_snes, snes_to_pc},
+    room::{Door, EnemyGfx, EnemyPopulation, Plm, RoomData, RoomHeader},
+    widgets::{read_block_entries, write_block_entries, TileEntry},
+};
+
+pub const EXTENSION: &str = "json";
+
+const VERSION: u32 = 1;
+
+// Fields of a block, in its level data word.
+const BLOCK_NUMBER_MASK: u16 = 0x03FF;
+const X_FLIP: u16 = 0x0400;
+const Y_FLIP: u16 = 0x0800;
+const BLOCK_TYPE_SHIFT: u16 = 12;
+
+/// Keys are SNES addresses in hexadecimal, except for tilesets which are by index.
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Project {
+    pub version: u32,
+    pub base_rom_crc32: String, // Of the unheadered ROM the edits apply to, in hexadecimal.
+    pub rooms: BTreeMap<String, RoomEntry>,
+    pub levels: BTreeMap<String, LevelEntry>,
+    pub palettes: BTreeMap<String, Vec<String>>, // Colors, as RRGGBB in hexadecimal.
+    pub tilesets: BTreeMap<usize, TilesetEntry>,
+    // Blocks of the tile tables, by block number in hexadecimal.
+    pub tile_tables: BTreeMap<String, BTreeMap<String, [TileEntry; 4]>>,
+    // Rows of pixels of the graphics sheets, as a hexadecimal digit by color index.
+    pub graphics: BTreeMap<String, Vec<String>>,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RoomEntry {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub header: Option<RoomHeader>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub doors: BTreeMap<String, Door>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub states: BTreeMap<String, StateEntry>,
+}
+
+/// Lists shared by several states are repeated in each of them.
+#[derive(Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StateEntry {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tileset: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enemy_population: Option<EnemyPopulation>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enemy_set: Option<Vec<EnemyGfx>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub plm_set: Option<Vec<Plm>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scrolls: Option<Vec<u8>>,
+}
+
+/// Layers are rows of blocks, as level data words in hexadecimal, and their BTS.
+#[derive(PartialEq, Serialize, Deserialize)]
+pub struct LevelEntry {
+    pub width: usize, // In blocks.
+    pub layer1: Vec<String>,
+    pub bts: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub layer2: Vec<String>,
+}
+
+/// SNES addresses of the data used by a tileset, in hexadecimal.
+#[derive(PartialEq, Serialize, Deserialize)]
+pub struct TilesetEntry {
+    pub tile_table: String,
+    pub graphics: String,
+    pub palette: String,
+}
+
+impl Project {
+    /// Everything edited in `sm` and `room_data`, since they were loaded from `base_rom`.
+    pub fn diff(base_rom: &[u8], sm: &SuperMetroid, room_data: &RoomData) -> Result<Self, String> {
+        let base = load_rom(base_rom)?;
+        let base_room_data = RoomData::load(&base);
+
+        let mut project = Project {
+            version: VERSION,
+            base_rom_crc32: format!("{:08X}", rom::crc32(base_rom)),
+            ..Default::default()
+        };
+
+        for (room_address, room) in sm.rooms.iter() {
+            let Some(base_room) = base.rooms.get(room_address) else {continue};
+            let mut entry = RoomEntry::default();
+
+            let header = RoomHeader::from_room(room);
+            if header != RoomHeader::from_room(base_room) {
+                entry.header = Some(header);
+            }
+
+            for address in room_data.room_doors.get(room_address).into_iter().flatten() {
+                let door = room_data.doors[address];
+                if base_room_data.doors.get(address) != Some(&door) {
+                    entry.doors.insert(hex(*address), door);
+                }
+            }
+
+            for state_condition in room.state_conditions.iter() {
+                let state_address = state_condition.state_address as usize;
+                let (Some(state), Some(base_state)) = (
+                    sm.states.get(&state_address),
+                    base.states.get(&state_address),
+                ) else {
+                    continue;
+                };
+
+                let state_entry = StateEntry {
+                    tileset: (state.tileset != base_state.tileset).then_some(state.tileset),
+                    enemy_population: changed_list(
+                        &room_data.state_enemy_populations,
+                        &room_data.enemy_populations,
+                        &base_room_data.enemy_populations,
+                        state_address,
+                    ),
+                    enemy_set: changed_list(
+                        &room_data.state_enemy_sets,
+                        &room_data.enemy_sets,
+                        &base_room_data.enemy_sets,
+                        state_address,
+                    ),
+                    plm_set: changed_list(
+                        &room_data.state_plm_sets,
+                        &room_data.plm_sets,
+                        &base_room_data.plm_sets,
+                        state_address,
+                    ),
+                    scrolls: changed_list(
+                        &room_data.state_scrolls,
+                        &room_data.scrolls,
+                        &base_room_data.scrolls,
+                        state_address,
+                    ),
+                };
+                if state_entry != StateEntry::default() {
+                    entry.states.insert(hex(state_address), state_entry);
+                }
+            }
+
+            if entry.header.is_some() || !entry.doors.is_empty() || !entry.states.is_empty() {
+                project.rooms.insert(hex(*room_address), entry);
+            }
+        }
+
+        let level_widths = level_widths(sm);
+        for (address, level) in sm.levels.iter() {
+            let width = level_widths
+                .get(address)
+                .copied()
+                .unwrap_or(level.layer1.len());
+            let entry = LevelEntry::new(level, width);
+            let base_entry = base
+                .levels
+                .get(address)
+                .map(|base| LevelEntry::new(base, width));
+            if base_entry.as_ref() != Some(&entry) {
+                project.levels.insert(hex(*address), entry);
+            }
+        }
+
+        for (address, palette) in sm.palettes.iter() {
+            let colors = palette_colors(palette, None);
+            if base
+                .palettes
+                .get(address)
+                .map(|base| palette_colors(base, None))
+                != Some(colors)
+            {
+                project
+                    .palettes
+                    .insert(hex(*address), encode_colors(palette));
+            }
+        }
+
+        for (index, tileset) in sm.tilesets.iter().enumerate() {
+            let entry = TilesetEntry::new(tileset);
+            if base.tilesets.get(index).map(TilesetEntry::new).as_ref() != Some(&entry) {
+                project.tilesets.insert(index, entry);
+            }
+        }
+
+        for (address, tile_table) in sm.tile_tables.iter() {
+            let base_tile_table = base.tile_tables.get(address);
+            let blocks: BTreeMap<String, [TileEntry; 4]> = (0..tile_table.tiles.len())
+                .map(|block| (block, read_block_entries(tile_table, block)))
+                .filter(|(block, entries)| {
+                    base_tile_table
+                        .filter(|base| *block < base.tiles.len())
+                        .map_or(true, |base| read_block_entries(base, *block) != *entries)
+                })
+                .map(|(block, entries)| (format!("{:03X}", block), entries))
+                .collect();
+            if !blocks.is_empty() {
+                project.tile_tables.insert(hex(*address), blocks);
+            }
+        }
+
+        for (address, graphics) in sm.graphics.iter() {
+            let rows = encode_graphics(graphics);
+            if base.graphics.get(address).map(encode_graphics).as_ref() != Some(&rows) {
+                project.graphics.insert(hex(*address), rows);
+            }
+        }
+
+        Ok(project)
+    }
+
+    /// Load `base_rom` and apply the edits of the project to it.
+    pub fn build(&self, base_rom: &[u8]) -> Result<(SuperMetroid, RoomData), String> {
+        if self.version > VERSION {
+            return Err(format!("Unsupported project version {}", self.version));
+        }
+        let crc32 = format!("{:08X}", rom::crc32(base_rom));
+        if !self.base_rom_crc32.eq_ignore_ascii_case(&crc32) {
+            return Err(format!(
+                "The project was made from a ROM with CRC32 {}, not {}",
+                self.base_rom_crc32, crc32
+            ));
+        }
+
+        let mut sm = load_rom(base_rom)?;
+        let mut room_data = RoomData::load(&sm);
+
+        for (key, entry) in self.rooms.iter() {
+            let room_address = parse_address(key)?;
+            let Some(room) = sm.rooms.get_mut(&room_address) else {
+                return Err(format!("Unknown room {}", key));
+            };
+            if let Some(header) = entry.header {
+                header.write(room);
+            }
+
+            for (key, door) in entry.doors.iter() {
+                room_data.doors.insert(parse_address(key)?, *door);
+            }
+
+            for (key, state_entry) in entry.states.iter() {
+                let state_address = parse_address(key)?;
+                let Some(state) = sm.states.get_mut(&state_address) else {
+                    return Err(format!("Unknown state {}", key));
+                };
+                if let Some(tileset) = state_entry.tileset {
+                    state.tileset = tileset;
+                }
+
+                let lists = [
+                    (
+                        state_entry.enemy_population.is_some(),
+                        &room_data.state_enemy_populations,
+                    ),
+                    (state_entry.enemy_set.is_some(), &room_data.state_enemy_sets),
+                    (state_entry.plm_set.is_some(), &room_data.state_plm_sets),
+                    (state_entry.scrolls.is_some(), &room_data.state_scrolls),
+                ];
+                if lists
+                    .iter()
+                    .any(|(edited, addresses)| *edited && !addresses.contains_key(&state_address))
+                {
+                    return Err(format!("State {} has no such list to edit", key));
+                }
+
+                if let Some(enemy_population) = &state_entry.enemy_population {
+                    let address = room_data.state_enemy_populations[&state_address];
+                    room_data
+                        .enemy_populations
+                        .insert(address, enemy_population.clone());
+                }
+                if let Some(enemy_set) = &state_entry.enemy_set {
+                    let address = room_data.state_enemy_sets[&state_address];
+                    room_data.enemy_sets.insert(address, enemy_set.clone());
+                }
+                if let Some(plm_set) = &state_entry.plm_set {
+                    let address = room_data.state_plm_sets[&state_address];
+                    room_data.plm_sets.insert(address, plm_set.clone());
+                }
+                if let Some(scrolls) = &state_entry.scrolls {
+                    let address = room_data.state_scrolls[&state_address];
+                    room_data.scrolls.insert(address, scrolls.clone());
+                }
+            }
+        }
+
+        for (key, entry) in self.levels.iter() {
+            let Some(level) = sm.levels.get_mut(&parse_address(key)?) else {
+                return Err(format!("Unknown level {}", key));
+            };
+            entry
+                .write(level)
+                .map_err(|e| format!("Invalid level {}: {}", key, e))?;
+        }
+
+        for (key, colors) in self.palettes.iter() {
+            let Some(palette) = sm.palettes.get_mut(&parse_address(key)?) else {
+                return Err(format!("Unknown palette {}", key));
+            };
+            let colors =
+                decode_colors(colors).map_err(|e| format!("Invalid palette {}: {}", key, e))?;
+            set_palette_colors(palette, None, &colors);
+        }
+
+        for (index, entry) in self.tilesets.iter() {
+            let Some(tileset) = sm.tilesets.get_mut(*index) else {
+                return Err(format!("Unknown tileset {}", index));
+            };
+            tileset.tile_table = parse_address(&entry.tile_table)? as _;
+            tileset.graphic = parse_address(&entry.graphics)? as _;
+            tileset.palette = parse_address(&entry.palette)? as _;
+        }
+
+        for (key, blocks) in self.tile_tables.iter() {
+            let Some(tile_table) = sm.tile_tables.get_mut(&parse_address(key)?) else {
+                return Err(format!("Unknown tile table {}", key));
+            };
+            for (block, entries) in blocks.iter() {
+                let block = usize::from_str_radix(block, 16)
+                    .ok()
+                    .filter(|block| *block < tile_table.tiles.len())
+                    .ok_or_else(|| format!("Invalid block {} of tile table {}", block, key))?;
+                write_block_entries(tile_table, block, entries);
+            }
+        }
+
+        for (key, rows) in self.graphics.iter() {
+            let Some(graphics) = sm.graphics.get_mut(&parse_address(key)?) else {
+                return Err(format!("Unknown graphics {}", key));
+            };
+            decode_graphics(graphics, rows)
+                .map_err(|e| format!("Invalid graphics {}: {}", key, e))?;
+        }
+
+        Ok((sm, room_data))
+    }
+
+    pub fn to_json(&self) -> Result<Vec<u8>, String> {
+        serde_json::to_vec_pretty(self).map_err(|e| e.to_string())
+    }
+
+    pub fn from_json(bytes: &[u8]) -> Result<Self, String> {
+        serde_json::from_slice(bytes).map_err(|e| e.to_string())
+    }
+}
+
+impl LevelEntry {
+    fn new(level: &LevelData, width: usize) -> Self {
+        let width = width.max(1);
+        let blocks = |layer: &[Block]| -> Vec<String> {
+            let words: Vec<String> = layer
+                .iter()
+                .map(|block| format!("{:04X}", block_word(block)))
+                .collect();
+            words.chunks(width).map(|row| row.join(" ")).collect()
+        };
+        let bts: Vec<String> = level.bts.iter().map(|bts| format!("{:02X}", bts)).collect();
+
+        Self {
+            width,
+            layer1: blocks(&level.layer1),
+            bts: bts.chunks(width).map(|row| row.join(" ")).collect(),
+            layer2: blocks(&level.layer2),
+        }
+    }
+
+    fn write(&self, level: &mut LevelData) -> Result<(), String> {
+        let layer1: Vec<u16> = parse_rows(&self.layer1)?;
+        let bts: Vec<u8> = parse_rows(&self.bts)?;
+        let layer2: Vec<u16> = parse_rows(&self.layer2)?;
+        if bts.len() != layer1.len() || (!layer2.is_empty() && layer2.len() != layer1.len()) {
+            return Err("Layers have different sizes".to_string());
+        }
+
+        level.layer1 = layer1.into_iter().map(word_block).collect();
+        level.bts = bts;
+        level.layer2 = layer2.into_iter().map(word_block).collect();
+        Ok(())
+    }
+}
+
+impl TilesetEntry {
+    fn new(tileset: &Tileset) -> Self {
+        Self {
+            tile_table: hex(tileset.tile_table as usize),
+            graphics: hex(tileset.graphic as usize),
+            palette: hex(tileset.palette as usize),
+        }
+    }
+}
+
+fn load_rom(rom: &[u8]) -> Result<SuperMetroid, String> {
+    zen::super_metroid::load_unheadered_rom(rom.to_vec()).map_err(|e| format!("{:?}", e))
+}
+
+fn hex(address: usize) -> String {
+    format!("{:06X}", pc_to_snes(address))
+}
+
+fn parse_address(key: &str) -> Result<usize, String> {
+    usize::from_str_radix(key, 16)
+        .map(snes_to_pc)
+        .map_err(|e| format!("Invalid address {}: {}", key, e))
+}
+
+/// Values of all the rows, in hexadecimal and separated by spaces.
+fn parse_rows<T: TryFrom<u32>>(rows: &[String]) -> Result<Vec<T>, String> {
+    rows.iter()
+        .flat_map(|row| row.split_whitespace())
+        .map(|value| {
+            u32::from_str_radix(value, 16)
+                .ok()
+                .and_then(|number| T::try_from(number).ok())
+                .ok_or_else(|| format!("Invalid value {}", value))
+        })
+        .collect()
+}
+
+/// The list used by a state, if it differs from the base ROM.
+fn changed_list<T: Clone + PartialEq>(
+    lists_by_state: &HashMap<usize, usize>,
+    lists: &BTreeMap<usize, T>,
+    base_lists: &BTreeMap<usize, T>,
+    state_address: usize,
+) -> Option<T> {
+    let address = lists_by_state.get(&state_address)?;
+    let list = lists.get(address)?;
+    (base_lists.get(address) != Some(list)).then(|| list.clone())
+}
+
+/// Width in blocks of the levels, from the rooms using them.
+fn level_widths(sm: &SuperMetroid) -> HashMap<usize, usize> {
+    let mut widths = HashMap::new();
+    for room in sm.rooms.values() {
+        let width = RoomHeader::from_room(room).size_in_blocks()[0];
+        for state_condition in room.state_conditions.iter() {
+            if let Some(state) = sm.states.get(&(state_condition.state_address as usize)) {
+                widths.insert(state.level_address as usize, width);
+            }
+        }
+    }
+    widths
+}
+
+fn block_word(block: &Block) -> u16 {
+    let mut word = block.block_number & BLOCK_NUMBER_MASK;
+    if block.x_flip {
+        word |= X_FLIP;
+    }
+    if block.y_flip {
+        word |= Y_FLIP;
+    }
+    word | (block.block_type as u16) << BLOCK_TYPE_SHIFT
+}
+
+fn word_block(word: u16) -> Block {
+    Block {
+        block_number: word & BLOCK_NUMBER_MASK,
+        x_flip: word & X_FLIP != 0,
+        y_flip: word & Y_FLIP != 0,
+        block_type: BlockType::from((word >> BLOCK_TYPE_SHIFT) as u8),
+    }
+}
+
+fn encode_colors(palette: &Palette) -> Vec<String> {
+    palette_colors(palette, None)
+        .iter()
+        .map(|color| format!("{:02X}{:02X}{:02X}", color.r, color.g, color.b))
+        .collect()
+}
+
+fn decode_colors(colors: &[String]) -> Result<Vec<Rgb888>, String> {
+    colors
+        .iter()
+        .map(|color| {
+            let rgb = u32::from_str_radix(color, 16).map_err(|e| format!("{}: {}", color, e))?;
+            let [_, r, g, b] = rgb.to_be_bytes();
+            Ok(Rgb888 { r, g, b })
+        })
+        .collect()
+}
+
+fn encode_graphics(graphics: &Gfx) -> Vec<String> {
+    graphics
+        .to_indexed_colors()
+        .chunks(graphics.size()[0].max(1))
+        .map(|row| row.iter().map(|index| format!("{:X}", index)).collect())
+        .collect()
+}
+
+fn decode_graphics(graphics: &mut Gfx, rows: &[String]) -> Result<(), String> {
+    let [width, height] = graphics.size();
+    if rows.len() != height || rows.iter().any(|row| row.len() != width) {
+        return Err(format!("Expected {}x{} pixels", width, height));
+    }
+
+    for (y, row) in rows.iter().enumerate() {
+        for (x, digit) in row.chars().enumerate() {
+            let Some(index) = digit.to_digit(16) else {
+                return Err(format!("Invalid color index {}", digit));
+            };
+            write_pixel(graphics, [x, y], index as u8);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,68 @@
+//! ROMs opened recently, offered at startup and in the File menu.
+//!
+//! Natively, ROMs are reopened from their path. Browsers can't reopen a file by its name,
+//! so on web the last opened ROM is kept in local storage instead, and is the only one listed.
+
+#[cfg(not(target_arch = "wasm32"))]
+const MAX_RECENT_ROMS: usize = 8;
+#[cfg(target_arch = "wasm32")]
+const MAX_RECENT_ROMS: usize = 1; // Local storage only fits one ROM.
+
+/// Key of the list in the app storage, persisted with the `persistence` feature.
+pub const STORAGE_KEY: &str = "recent_roms";
+
+#[derive(Default)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub struct RecentRoms {
+    paths: Vec<String>, // Most recent first. File names only, on web.
+}
+
+impl RecentRoms {
+    pub fn add(&mut self, path: String) {
+        self.paths.retain(|recent| *recent != path);
+        self.paths.insert(0, path);
+        self.paths.truncate(MAX_RECENT_ROMS);
+    }
+
+    pub fn remove(&mut self, path: &str) {
+        self.paths.retain(|recent| recent != path);
+    }
+
+    pub fn paths(&self) -> &[String] {
+        // The list may have been persisted with a larger limit.
+        &self.paths[..self.paths.len().min(MAX_RECENT_ROMS)]
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+const CACHED_ROM_KEY: &str = "zen_sm_cached_rom";
+
+/// Keep the ROM in local storage, to reopen it from the recent list.
+/// Returns false when it doesn't fit in the storage.
+#[cfg(target_arch = "wasm32")]
+pub fn cache_rom(name: &str, data: &[u8]) -> bool {
+    let Some(window) = web_sys::window() else {return false};
+    let Ok(Some(storage)) = window.local_storage() else {return false};
+
+    // Local storage only holds strings, so store the bytes in base64.
+    let binary: String = data.iter().map(|byte| *byte as char).collect();
+    let Ok(encoded) = window.btoa(&binary) else {return false};
+    storage
+        .set_item(CACHED_ROM_KEY, &format!("{}\n{}", name, encoded))
+        .is_ok()
+}
+
+/// The ROM kept in local storage, if it is the one named `name`.
+#[cfg(target_arch = "wasm32")]
+pub fn cached_rom(name: &str) -> Option<Vec<u8>> {
+    let window = web_sys::window()?;
+    let storage = window.local_storage().ok()??;
+    let cached = storage.get_item(CACHED_ROM_KEY).ok()??;
+
+    let (cached_name, encoded) = cached.split_once('\n')?;
+    if cached_name != name {
+        return None;
+    }
+    let binary = window.atob(encoded).ok()?;
+    Some(binary.chars().map(|char| char as u8).collect())
+}
@@ -0,0 +1,215 @@
+//! Helpers to read and write data directly in the ROM.
+//!
+//! Addresses used as keys by `SuperMetroid` are PC offsets into the unheadered ROM,
+//! while pointers stored inside the ROM are LoROM SNES addresses.
+
+use std::ops::Range;
+
+pub const ROOM_BANK: usize = 0x8F_0000;
+pub const DOOR_BANK: usize = 0x83_0000;
+pub const ENEMY_POPULATION_BANK: usize = 0xA1_0000;
+pub const ENEMY_SET_BANK: usize = 0xB4_0000;
+
+// Start of the unused space at the end of the banks, in the vanilla ROM.
+pub const ENEMY_POPULATION_FREE_SPACE: usize = ENEMY_POPULATION_BANK | 0xEBD1;
+pub const ENEMY_SET_FREE_SPACE: usize = ENEMY_SET_BANK | 0xF4B8;
+pub const ROOM_FREE_SPACE: usize = ROOM_BANK | 0xE99B;
+
+pub fn snes_to_pc(address: usize) -> usize {
+    ((address & 0x7F_0000) >> 1) | (address & 0x7FFF)
+}
+
+pub fn pc_to_snes(address: usize) -> usize {
+    ((address << 1) & 0x7F_0000) | (address & 0x7FFF) | 0x80_8000
+}
+
+pub fn read_u16(rom: &[u8], address: usize) -> u16 {
+    u16::from_le_bytes([rom[address], rom[address + 1]])
+}
+
+pub fn write_u16(rom: &mut [u8], address: usize, value: u16) {
+    rom[address..address + 2].copy_from_slice(&value.to_le_bytes());
+}
+
+/// Unused space at the end of a bank, where lists that outgrew their location are moved to.
+pub struct FreeSpace {
+    next: usize,
+    end: usize,
+}
+
+impl FreeSpace {
+    /// Free space from `free_space_start`, a SNES address, to the end of its bank.
+    /// Skips any data already moved there, given as PC ranges.
+    pub fn new(free_space_start: usize, used: impl Iterator<Item = Range<usize>>) -> Self {
+        let start = snes_to_pc(free_space_start);
+        let end = snes_to_pc(free_space_start | 0xFFFF) + 1;
+        let next = used
+            .filter(|range| range.end > start && range.start < end)
+            .map(|range| range.end)
+            .fold(start, usize::max);
+
+        Self { next, end }
+    }
+
+    pub fn allocate(&mut self, size: usize) -> Option<usize> {
+        let address = self.next;
+        (address + size <= self.end).then(|| {
+            self.next += size;
+            address
+        })
+    }
+}
+
+/// Write a list at its address when it still fits in the `capacity` of its original
+/// location, or in the free space otherwise. Returns where the list was written.
+pub fn write_list(
+    rom: &mut [u8],
+    address: usize,
+    capacity: usize,
+    bytes: &[u8],
+    free_space: &mut FreeSpace,
+) -> Option<usize> {
+    let address = if bytes.len() <= capacity {
+        address
+    } else {
+        free_space.allocate(bytes.len())?
+    };
+
+    rom[address..address + bytes.len()].copy_from_slice(bytes);
+    Some(address)
+}
+
+// Header some copiers add in front of the ROM, which `SuperMetroid` doesn't expect.
+const COPIER_HEADER_SIZE: usize = 0x200;
+
+// Checksum of the internal header, for LoROM.
+const CHECKSUM_COMPLEMENT: usize = 0x7FDC;
+const CHECKSUM: usize = 0x7FDE;
+
+/// Split a ROM file into its copier header, if it has one, and the ROM itself.
+pub fn split_copier_header(data: &[u8]) -> (Option<Vec<u8>>, Vec<u8>) {
+    if data.len() % 0x8000 == COPIER_HEADER_SIZE {
+        let (header, rom) = data.split_at(COPIER_HEADER_SIZE);
+        (Some(header.to_vec()), rom.to_vec())
+    } else {
+        (None, data.to_vec())
+    }
+}
+
+/// CRC32 of a ROM, as commonly used to identify it.
+pub fn crc32(rom: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in rom {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+/// Recompute the checksum of the internal header, and its complement.
+pub fn fix_checksum(rom: &mut [u8]) {
+    if rom.len() < CHECKSUM + 2 {
+        return;
+    }
+
+    write_u16(rom, CHECKSUM_COMPLEMENT, 0xFFFF);
+    write_u16(rom, CHECKSUM, 0x0000);
+
+    let checksum = checksum(rom);
+    write_u16(rom, CHECKSUM_COMPLEMENT, !checksum);
+    write_u16(rom, CHECKSUM, checksum);
+}
+
+/// Sum of all the bytes of the ROM. When its size isn't a power of two, the part past
+/// the largest power of two is mirrored until it fills it, as the SNES does.
+fn checksum(rom: &[u8]) -> u16 {
+    mirrored_sum(rom, rom.len().next_power_of_two())
+}
+
+/// Sum of `bytes` repeated until they fill `size`, a power of two. Like the whole ROM, a part
+/// whose size isn't a power of two is itself split, and its remainder mirrored.
+fn mirrored_sum(bytes: &[u8], size: usize) -> u16 {
+    if bytes.is_empty() {
+        return 0;
+    }
+
+    let base = if bytes.len().is_power_of_two() {
+        bytes.len()
+    } else {
+        bytes.len().next_power_of_two() / 2
+    };
+    let (base_part, mirrored_part) = bytes.split_at(base);
+    let block_size = if mirrored_part.is_empty() {
+        base
+    } else {
+        base * 2
+    };
+
+    let block_sum = base_part
+        .iter()
+        .fold(0u16, |sum, byte| sum.wrapping_add(*byte as u16))
+        .wrapping_add(mirrored_sum(mirrored_part, base));
+    block_sum.wrapping_mul((size / block_size) as u16)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crc32_check_value() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+        assert_eq!(crc32(&[]), 0);
+    }
+
+    #[test]
+    fn split_copier_header_only_when_present() {
+        let (header, rom) = split_copier_header(&[0u8; 0x8200]);
+        assert_eq!(header.map(|header| header.len()), Some(0x200));
+        assert_eq!(rom.len(), 0x8000);
+
+        let (header, rom) = split_copier_header(&[0u8; 0x8000]);
+        assert!(header.is_none());
+        assert_eq!(rom.len(), 0x8000);
+    }
+
+    #[test]
+    fn fix_checksum_power_of_two() {
+        // Only the checksum and its complement aren't zero, summing to 0x01FE.
+        let mut rom = vec![0u8; 0x8000];
+        fix_checksum(&mut rom);
+        assert_eq!(read_u16(&rom, CHECKSUM), 0x01FE);
+        assert_eq!(read_u16(&rom, CHECKSUM_COMPLEMENT), 0xFE01);
+
+        // Fixing it again gives the same checksum.
+        fix_checksum(&mut rom);
+        assert_eq!(read_u16(&rom, CHECKSUM), 0x01FE);
+    }
+
+    #[test]
+    fn fix_checksum_mirrors_remainder() {
+        // 0x8000 bytes, then 0x4000 bytes mirrored twice to fill 0x8000 bytes.
+        let mut rom = vec![0u8; 0xC000];
+        rom[0x8000..].fill(1);
+        fix_checksum(&mut rom);
+        assert_eq!(read_u16(&rom, CHECKSUM), 0x01FE + 0x8000);
+    }
+
+    #[test]
+    fn fix_checksum_mirrors_remainder_recursively() {
+        // The 0x6000 bytes past 0x8000 are themselves 0x4000 bytes,
+        // then 0x2000 bytes mirrored twice to fill 0x4000 bytes.
+        let mut rom = vec![0u8; 0xE000];
+        rom[0x8000..0xC000].fill(1);
+        rom[0xC000..].fill(2);
+        fix_checksum(&mut rom);
+        assert_eq!(read_u16(&rom, CHECKSUM), 0x01FE + 0x4000 + 0x2000 * 2 * 2);
+        assert_eq!(
+            read_u16(&rom, CHECKSUM_COMPLEMENT),
+            !(0x01FE + 0x4000 + 0x2000 * 2 * 2)
+        );
+    }
+}
@@ -0,0 +1,101 @@
+use zen::super_metroid::level_data::{BlockType, LevelData};
+
+use crate::rom::{self, DOOR_BANK, ROOM_BANK};
+
+pub const DOOR_SIZE: usize = 12;
+
+// Offset of the door list pointer in the room header.
+const DOOR_LIST_POINTER: usize = 9;
+
+pub const DIRECTIONS: [&str; 4] = ["Right", "Left", "Down", "Up"];
+pub const DIRECTION_MASK: u8 = 0b011;
+pub const CLOSING_CAP: u8 = 0b100; // The door cap closes behind Samus.
+
+/// A door entry, from the door list of a room.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub struct Door {
+    pub destination_room: u16, // Pointer in the room bank.
+    pub bitflag: u8,
+    pub direction: u8,
+    pub cap_x: u8, // In blocks.
+    pub cap_y: u8, // In blocks.
+    pub screen_x: u8,
+    pub screen_y: u8,
+    pub spawn_distance: u16,
+    pub asm_pointer: u16,
+}
+
+impl Door {
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        Self {
+            destination_room: u16::from_le_bytes([bytes[0], bytes[1]]),
+            bitflag: bytes[2],
+            direction: bytes[3],
+            cap_x: bytes[4],
+            cap_y: bytes[5],
+            screen_x: bytes[6],
+            screen_y: bytes[7],
+            spawn_distance: u16::from_le_bytes([bytes[8], bytes[9]]),
+            asm_pointer: u16::from_le_bytes([bytes[10], bytes[11]]),
+        }
+    }
+
+    pub fn to_bytes(&self) -> [u8; DOOR_SIZE] {
+        let destination_room = self.destination_room.to_le_bytes();
+        let spawn_distance = self.spawn_distance.to_le_bytes();
+        let asm_pointer = self.asm_pointer.to_le_bytes();
+        [
+            destination_room[0],
+            destination_room[1],
+            self.bitflag,
+            self.direction,
+            self.cap_x,
+            self.cap_y,
+            self.screen_x,
+            self.screen_y,
+            spawn_distance[0],
+            spawn_distance[1],
+            asm_pointer[0],
+            asm_pointer[1],
+        ]
+    }
+
+    pub fn destination_room_address(&self) -> usize {
+        rom::snes_to_pc(ROOM_BANK | self.destination_room as usize)
+    }
+}
+
+/// Addresses of the door entries of a room.
+///
+/// The door list has no terminator, so its length comes from the highest door index
+/// used by the door blocks in the room levels.
+pub fn load_door_addresses<'a>(
+    rom: &[u8],
+    room_address: usize,
+    levels: impl Iterator<Item = &'a LevelData>,
+) -> Vec<usize> {
+    let number_of_doors = levels
+        .flat_map(|level| level.layer1.iter().zip(level.bts.iter()))
+        .filter(|(block, _)| block.block_type == BlockType::Door)
+        .map(|(_, bts)| *bts as usize + 1)
+        .max()
+        .unwrap_or(0);
+
+    let door_list =
+        rom::snes_to_pc(ROOM_BANK | rom::read_u16(rom, room_address + DOOR_LIST_POINTER) as usize);
+
+    (0..number_of_doors)
+        .map(|index| rom::read_u16(rom, door_list + index * 2) as usize)
+        .take_while(|pointer| *pointer >= 0x8000)
+        .map(|pointer| rom::snes_to_pc(DOOR_BANK | pointer))
+        .collect()
+}
+
+pub fn load_door(rom: &[u8], address: usize) -> Door {
+    Door::from_bytes(&rom[address..address + DOOR_SIZE])
+}
+
+pub fn save_door(rom: &mut [u8], address: usize, door: &Door) {
+    rom[address..address + DOOR_SIZE].copy_from_slice(&door.to_bytes());
+}
@@ -0,0 +1,153 @@
+use std::ops::RangeInclusive;
+
+use crate::rom::{self, ENEMY_POPULATION_BANK, ENEMY_SET_BANK};
+
+pub const ENEMY_SIZE: usize = 16;
+pub const ENEMY_GFX_SIZE: usize = 4;
+
+const LIST_TERMINATOR: u16 = 0xFFFF;
+
+/// Enemy IDs pointing into bank $A0, short of the list terminator.
+pub const ENEMY_IDS: RangeInclusive<u16> = 0x8000..=LIST_TERMINATOR - 1;
+
+// Offsets of the enemy pointers in the state header.
+const ENEMY_POPULATION_POINTER: usize = 8;
+const ENEMY_SET_POINTER: usize = 10;
+
+/// An enemy placed in a room, from the enemy population of a state.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub struct Enemy {
+    pub id: u16, // Pointer to the enemy header, in bank $A0.
+    pub x: u16,  // In pixels, at the center of the enemy.
+    pub y: u16,  // In pixels, at the center of the enemy.
+    pub initial_parameter: u16,
+    pub properties: u16,
+    pub extra_properties: u16,
+    pub parameter1: u16,
+    pub parameter2: u16,
+}
+
+impl Enemy {
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        let word = |offset: usize| u16::from_le_bytes([bytes[offset], bytes[offset + 1]]);
+        Self {
+            id: word(0),
+            x: word(2),
+            y: word(4),
+            initial_parameter: word(6),
+            properties: word(8),
+            extra_properties: word(10),
+            parameter1: word(12),
+            parameter2: word(14),
+        }
+    }
+
+    pub fn to_bytes(&self) -> [u8; ENEMY_SIZE] {
+        let mut bytes = [0; ENEMY_SIZE];
+        let words = [
+            self.id,
+            self.x,
+            self.y,
+            self.initial_parameter,
+            self.properties,
+            self.extra_properties,
+            self.parameter1,
+            self.parameter2,
+        ];
+        for (chunk, word) in bytes.chunks_mut(2).zip(words) {
+            chunk.copy_from_slice(&word.to_le_bytes());
+        }
+        bytes
+    }
+}
+
+/// The enemies of a state.
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub struct EnemyPopulation {
+    pub enemies: Vec<Enemy>,
+    pub kill_count: u8, // Enemies to kill before the room is cleared.
+}
+
+impl EnemyPopulation {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes: Vec<u8> = self
+            .enemies
+            .iter()
+            .flat_map(|enemy| enemy.to_bytes())
+            .collect();
+        bytes.extend(LIST_TERMINATOR.to_le_bytes());
+        bytes.push(self.kill_count);
+        bytes
+    }
+}
+
+/// Graphics of an enemy loaded by a state, from its enemy set.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub struct EnemyGfx {
+    pub id: u16,      // Pointer to the enemy header, in bank $A0.
+    pub palette: u16, // Sprite palette used by the enemy.
+}
+
+pub fn enemy_set_to_bytes(enemy_set: &[EnemyGfx]) -> Vec<u8> {
+    let mut bytes: Vec<u8> = enemy_set
+        .iter()
+        .flat_map(|enemy_gfx| [enemy_gfx.id.to_le_bytes(), enemy_gfx.palette.to_le_bytes()])
+        .flatten()
+        .collect();
+    bytes.extend(LIST_TERMINATOR.to_le_bytes());
+    bytes
+}
+
+pub fn enemy_population_address(rom: &[u8], state_address: usize) -> Option<usize> {
+    let pointer = rom::read_u16(rom, state_address + ENEMY_POPULATION_POINTER) as usize;
+    (pointer >= 0x8000).then(|| rom::snes_to_pc(ENEMY_POPULATION_BANK | pointer))
+}
+
+pub fn enemy_set_address(rom: &[u8], state_address: usize) -> Option<usize> {
+    let pointer = rom::read_u16(rom, state_address + ENEMY_SET_POINTER) as usize;
+    (pointer >= 0x8000).then(|| rom::snes_to_pc(ENEMY_SET_BANK | pointer))
+}
+
+pub fn set_enemy_population_address(rom: &mut [u8], state_address: usize, address: usize) {
+    let pointer = rom::pc_to_snes(address) as u16;
+    rom::write_u16(rom, state_address + ENEMY_POPULATION_POINTER, pointer);
+}
+
+pub fn set_enemy_set_address(rom: &mut [u8], state_address: usize, address: usize) {
+    let pointer = rom::pc_to_snes(address) as u16;
+    rom::write_u16(rom, state_address + ENEMY_SET_POINTER, pointer);
+}
+
+/// Returns the enemy population, and its size in the ROM.
+pub fn load_enemy_population(rom: &[u8], address: usize) -> (EnemyPopulation, usize) {
+    let enemies: Vec<Enemy> = (address..)
+        .step_by(ENEMY_SIZE)
+        .take_while(|address| rom::read_u16(rom, *address) != LIST_TERMINATOR)
+        .map(|address| Enemy::from_bytes(&rom[address..address + ENEMY_SIZE]))
+        .collect();
+
+    let terminator = address + enemies.len() * ENEMY_SIZE;
+    let population = EnemyPopulation {
+        enemies,
+        kill_count: rom[terminator + 2],
+    };
+    (population, terminator + 3 - address)
+}
+
+/// Returns the enemy set, and its size in the ROM.
+pub fn load_enemy_set(rom: &[u8], address: usize) -> (Vec<EnemyGfx>, usize) {
+    let enemy_set: Vec<EnemyGfx> = (address..)
+        .step_by(ENEMY_GFX_SIZE)
+        .take_while(|address| rom::read_u16(rom, *address) != LIST_TERMINATOR)
+        .map(|address| EnemyGfx {
+            id: rom::read_u16(rom, address),
+            palette: rom::read_u16(rom, address + 2),
+        })
+        .collect();
+
+    let size = enemy_set.len() * ENEMY_GFX_SIZE + 2;
+    (enemy_set, size)
+}
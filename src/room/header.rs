@@ -0,0 +1,93 @@
+use zen::super_metroid::{
+    level_data::{Block, LevelData},
+    room::Room,
+};
+
+use super::{scroll::BLUE, SCREEN_SIZE_IN_BLOCKS};
+
+/// The editable fields of a room header.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub struct RoomHeader {
+    pub area: u8,
+    pub map_x: u8,
+    pub map_y: u8,
+    pub width: u8,  // In screens.
+    pub height: u8, // In screens.
+    pub up_scroller: u8,
+    pub down_scroller: u8,
+    pub special_graphics_bits: u8,
+}
+
+impl RoomHeader {
+    pub fn from_room(room: &Room) -> Self {
+        Self {
+            area: room.area,
+            map_x: room.map_x,
+            map_y: room.map_y,
+            width: room.width,
+            height: room.height,
+            up_scroller: room.up_scroller,
+            down_scroller: room.down_scroller,
+            special_graphics_bits: room.special_graphics_bits,
+        }
+    }
+
+    pub fn write(&self, room: &mut Room) {
+        room.area = self.area;
+        room.map_x = self.map_x;
+        room.map_y = self.map_y;
+        room.width = self.width;
+        room.height = self.height;
+        room.up_scroller = self.up_scroller;
+        room.down_scroller = self.down_scroller;
+        room.special_graphics_bits = self.special_graphics_bits;
+    }
+
+    pub fn size_in_screens(&self) -> [usize; 2] {
+        [self.width as usize, self.height as usize]
+    }
+
+    pub fn size_in_blocks(&self) -> [usize; 2] {
+        [
+            self.width as usize * SCREEN_SIZE_IN_BLOCKS,
+            self.height as usize * SCREEN_SIZE_IN_BLOCKS,
+        ]
+    }
+}
+
+/// Reallocate the level layers to a new size, keeping the existing blocks anchored top left.
+pub fn resize_level(level: &LevelData, old_size: [usize; 2], new_size: [usize; 2]) -> LevelData {
+    let mut resized = level.clone();
+
+    resized.layer1 = resize_layer(&level.layer1, old_size, new_size, Block::default());
+    resized.bts = resize_layer(&level.bts, old_size, new_size, 0);
+    if !level.layer2.is_empty() {
+        resized.layer2 = resize_layer(&level.layer2, old_size, new_size, Block::default());
+    }
+
+    resized
+}
+
+/// Reallocate the scroll values of the screens to a new size, anchored top left like the level.
+/// New screens are blue.
+pub fn resize_scrolls(scrolls: &[u8], old_size: [usize; 2], new_size: [usize; 2]) -> Vec<u8> {
+    resize_layer(scrolls, old_size, new_size, BLUE)
+}
+
+fn resize_layer<T: Copy>(
+    layer: &[T],
+    old_size: [usize; 2],
+    new_size: [usize; 2],
+    empty: T,
+) -> Vec<T> {
+    let mut resized = vec![empty; new_size[0] * new_size[1]];
+
+    let kept_width = old_size[0].min(new_size[0]);
+    for y in 0..old_size[1].min(new_size[1]) {
+        resized[y * new_size[0]..y * new_size[0] + kept_width]
+            .copy_from_slice(&layer[y * old_size[0]..y * old_size[0] + kept_width]);
+    }
+
+    resized
+}
@@ -0,0 +1,217 @@
+//! Room data edited by zen_sm on top of what `zen` loads.
+
+pub mod door;
+pub mod enemy;
+pub mod header;
+pub mod names;
+pub mod plm;
+pub mod scroll;
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    ops::Range,
+};
+
+use zen::super_metroid::SuperMetroid;
+
+use crate::rom::{
+    self, FreeSpace, ENEMY_POPULATION_FREE_SPACE, ENEMY_SET_FREE_SPACE, ROOM_FREE_SPACE,
+};
+
+pub use door::Door;
+pub use enemy::{Enemy, EnemyGfx, EnemyPopulation, ENEMY_IDS};
+pub use header::{resize_level, resize_scrolls, RoomHeader};
+pub use plm::{Plm, PlmKind};
+
+/// Rooms are made of screens of 16x16 blocks.
+pub const SCREEN_SIZE_IN_BLOCKS: usize = 16;
+
+/// Room data that `zen` does not load, read and written directly in the ROM.
+#[derive(Default)]
+pub struct RoomData {
+    pub doors: BTreeMap<usize, Door>,           // By door entry address.
+    pub room_doors: HashMap<usize, Vec<usize>>, // Door entry addresses, by room address.
+    pub enemy_populations: BTreeMap<usize, EnemyPopulation>, // By enemy population address.
+    pub state_enemy_populations: HashMap<usize, usize>, // Enemy population address, by state.
+    pub enemy_sets: BTreeMap<usize, Vec<EnemyGfx>>, // By enemy set address.
+    pub state_enemy_sets: HashMap<usize, usize>, // Enemy set address, by state.
+    pub plm_sets: BTreeMap<usize, Vec<Plm>>,    // By PLM set address.
+    pub state_plm_sets: HashMap<usize, usize>,  // PLM set address, by state.
+    pub scrolls: BTreeMap<usize, Vec<u8>>,      // By scroll data address.
+    pub state_scrolls: HashMap<usize, usize>,   // Scroll data address, by state.
+    pub fixed_scrolls: HashMap<usize, u8>,      // Scroll of all screens, for states without data.
+    list_sizes: HashMap<usize, usize>,          // Size of the lists in the loaded ROM, by address.
+}
+
+impl RoomData {
+    pub fn load(sm: &SuperMetroid) -> Self {
+        let mut room_data = Self::default();
+
+        for (room_address, room) in sm.rooms.iter() {
+            let levels = room
+                .state_conditions
+                .iter()
+                .filter_map(|state_condition| {
+                    sm.states.get(&(state_condition.state_address as usize))
+                })
+                .filter_map(|state| sm.levels.get(&(state.level_address as usize)));
+
+            let door_addresses = door::load_door_addresses(&sm.rom, *room_address, levels);
+            for address in door_addresses.iter() {
+                room_data
+                    .doors
+                    .insert(*address, door::load_door(&sm.rom, *address));
+            }
+            room_data.room_doors.insert(*room_address, door_addresses);
+
+            let screens = room.width as usize * room.height as usize;
+            for state_condition in room.state_conditions.iter() {
+                room_data.load_scrolls(&sm.rom, state_condition.state_address as usize, screens);
+            }
+        }
+
+        for state_address in sm.states.keys() {
+            room_data.load_enemies(&sm.rom, *state_address);
+            room_data.load_plms(&sm.rom, *state_address);
+        }
+
+        room_data
+    }
+
+    fn load_enemies(&mut self, rom: &[u8], state_address: usize) {
+        if let Some(address) = enemy::enemy_population_address(rom, state_address) {
+            let (population, size) = enemy::load_enemy_population(rom, address);
+            self.enemy_populations.insert(address, population);
+            self.state_enemy_populations.insert(state_address, address);
+            self.list_sizes.insert(address, size);
+        }
+
+        if let Some(address) = enemy::enemy_set_address(rom, state_address) {
+            let (enemy_set, size) = enemy::load_enemy_set(rom, address);
+            self.enemy_sets.insert(address, enemy_set);
+            self.state_enemy_sets.insert(state_address, address);
+            self.list_sizes.insert(address, size);
+        }
+    }
+
+    fn load_plms(&mut self, rom: &[u8], state_address: usize) {
+        if let Some(address) = plm::plm_set_address(rom, state_address) {
+            let (plm_set, size) = plm::load_plm_set(rom, address);
+            self.plm_sets.insert(address, plm_set);
+            self.state_plm_sets.insert(state_address, address);
+            self.list_sizes.insert(address, size);
+        }
+    }
+
+    fn load_scrolls(&mut self, rom: &[u8], state_address: usize, screens: usize) {
+        if let Some(address) = scroll::scrolls_address(rom, state_address) {
+            self.scrolls
+                .insert(address, scroll::load_scrolls(rom, address, screens));
+            self.state_scrolls.insert(state_address, address);
+            self.list_sizes.insert(address, screens);
+        } else if let Some(fixed_scroll) = scroll::fixed_scroll(rom, state_address) {
+            self.fixed_scrolls.insert(state_address, fixed_scroll);
+        }
+    }
+
+    /// Write the room data into the ROM. Done after `SuperMetroid::save_to_rom`.
+    pub fn save(&self, rom: &mut [u8]) {
+        for (address, door) in self.doors.iter() {
+            door::save_door(rom, *address, door);
+        }
+
+        self.save_lists(
+            rom,
+            self.enemy_populations
+                .iter()
+                .map(|(address, population)| (*address, population.to_bytes())),
+            &self.state_enemy_populations,
+            &mut FreeSpace::new(ENEMY_POPULATION_FREE_SPACE, self.used_space()),
+            enemy::set_enemy_population_address,
+        );
+        self.save_lists(
+            rom,
+            self.enemy_sets
+                .iter()
+                .map(|(address, enemy_set)| (*address, enemy::enemy_set_to_bytes(enemy_set))),
+            &self.state_enemy_sets,
+            &mut FreeSpace::new(ENEMY_SET_FREE_SPACE, self.used_space()),
+            enemy::set_enemy_set_address,
+        );
+
+        // PLM sets and scroll data share the free space of the room bank.
+        let mut room_free_space = FreeSpace::new(ROOM_FREE_SPACE, self.used_space());
+        self.save_lists(
+            rom,
+            self.plm_sets
+                .iter()
+                .map(|(address, plm_set)| (*address, plm::plm_set_to_bytes(plm_set))),
+            &self.state_plm_sets,
+            &mut room_free_space,
+            plm::set_plm_set_address,
+        );
+        self.save_lists(
+            rom,
+            self.scrolls
+                .iter()
+                .map(|(address, scrolls)| (*address, scrolls.clone())),
+            &self.state_scrolls,
+            &mut room_free_space,
+            scroll::set_scrolls_address,
+        );
+    }
+
+    /// Write the lists used by the states, moving the ones that outgrew their location
+    /// to the free space and repointing their states.
+    fn save_lists(
+        &self,
+        rom: &mut [u8],
+        lists: impl Iterator<Item = (usize, Vec<u8>)>,
+        lists_by_state: &HashMap<usize, usize>,
+        free_space: &mut FreeSpace,
+        set_address: fn(&mut [u8], usize, usize),
+    ) {
+        for (address, bytes) in lists {
+            let capacity = self.list_sizes[&address];
+            let new_address = rom::write_list(rom, address, capacity, &bytes, free_space);
+            let Some(new_address) = new_address else {
+                log::warn!("No free space left to move the list at {:x}", address);
+                continue;
+            };
+            for state_address in Self::states_using(lists_by_state, address) {
+                set_address(rom, state_address, new_address);
+            }
+        }
+    }
+
+    // Lists as found in the loaded ROM, to keep the free space already used by them.
+    fn used_space(&self) -> impl Iterator<Item = Range<usize>> + '_ {
+        self.list_sizes
+            .iter()
+            .map(|(address, size)| *address..address + size)
+    }
+
+    // States may share their lists, so all of them are repointed when a list is moved.
+    fn states_using(
+        lists_by_state: &HashMap<usize, usize>,
+        address: usize,
+    ) -> impl Iterator<Item = usize> + '_ {
+        lists_by_state
+            .iter()
+            .filter(move |(_, list_address)| **list_address == address)
+            .map(|(state_address, _)| *state_address)
+    }
+
+    /// The doors of a room, with their addresses, in door list order.
+    pub fn room_doors(&self, room_address: usize) -> Vec<(usize, Door)> {
+        self.room_doors
+            .get(&room_address)
+            .map(|addresses| {
+                addresses
+                    .iter()
+                    .map(|address| (*address, self.doors[address]))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
@@ -0,0 +1,46 @@
+//! Names of the vanilla rooms and areas.
+
+use std::collections::HashMap;
+
+use crate::rom;
+
+pub const AREA_NAMES: [&str; 8] = [
+    "Crateria",
+    "Brinstar",
+    "Norfair",
+    "Wrecked Ship",
+    "Maridia",
+    "Tourian",
+    "Ceres",
+    "Debug",
+];
+
+lazy_static::lazy_static! {
+    static ref VANILLA_NAMES: HashMap<usize, &'static str> =
+        parse_names(include_str!("../../data/room_names.txt"));
+}
+
+pub fn area_name(area: u8) -> &'static str {
+    AREA_NAMES
+        .get(area as usize)
+        .copied()
+        .unwrap_or("Unknown area")
+}
+
+/// The vanilla name of the room at PC address `room_address`.
+pub fn vanilla_name(room_address: usize) -> Option<&'static str> {
+    VANILLA_NAMES.get(&room_address).copied()
+}
+
+/// Lines of `<SNES address> <name>`, with blank lines and `#` comments skipped.
+fn parse_names(text: &'static str) -> HashMap<usize, &'static str> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let (address, name) = line.split_once(' ')?;
+            let address = usize::from_str_radix(address, 16).ok()?;
+            Some((rom::snes_to_pc(address), name.trim()))
+        })
+        .collect()
+}
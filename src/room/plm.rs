@@ -0,0 +1,98 @@
+use crate::rom::{self, ROOM_BANK};
+
+pub const PLM_SIZE: usize = 6;
+
+const LIST_TERMINATOR: u16 = 0x0000;
+
+// Offset of the PLM set pointer in the state header.
+const PLM_SET_POINTER: usize = 20;
+
+/// A post-load modification, like an item, a door cap or a station, from the PLM set of a state.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub struct Plm {
+    pub id: u16, // Pointer to the PLM header, in bank $84.
+    pub x: u8,   // In blocks.
+    pub y: u8,   // In blocks.
+    pub argument: u16,
+}
+
+impl Plm {
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        Self {
+            id: u16::from_le_bytes([bytes[0], bytes[1]]),
+            x: bytes[2],
+            y: bytes[3],
+            argument: u16::from_le_bytes([bytes[4], bytes[5]]),
+        }
+    }
+
+    pub fn to_bytes(&self) -> [u8; PLM_SIZE] {
+        let id = self.id.to_le_bytes();
+        let argument = self.argument.to_le_bytes();
+        [id[0], id[1], self.x, self.y, argument[0], argument[1]]
+    }
+
+    pub fn kind(&self) -> PlmKind {
+        match self.id {
+            0xB63B..=0xB647 | 0xB703 => PlmKind::Scroll,
+            0xB6D3..=0xB6EB | 0xB76F => PlmKind::Station,
+            0xC806..=0xC8CA => PlmKind::Door,
+            0xEED7..=0xEFD3 => PlmKind::Item,
+            _ => PlmKind::Other,
+        }
+    }
+}
+
+/// Groups of PLMs from the vanilla ROM, to tell them apart when drawn.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub enum PlmKind {
+    Item,
+    Door, // Door caps and gates.
+    Station,
+    Scroll,
+    Other,
+}
+
+impl PlmKind {
+    pub const ALL: [PlmKind; 5] = [
+        PlmKind::Item,
+        PlmKind::Door,
+        PlmKind::Station,
+        PlmKind::Scroll,
+        PlmKind::Other,
+    ];
+}
+
+/// PLMs with the terminator as ID would cut the set short, so they are left out.
+pub fn plm_set_to_bytes(plm_set: &[Plm]) -> Vec<u8> {
+    let mut bytes: Vec<u8> = plm_set
+        .iter()
+        .filter(|plm| plm.id != LIST_TERMINATOR)
+        .flat_map(|plm| plm.to_bytes())
+        .collect();
+    bytes.extend(LIST_TERMINATOR.to_le_bytes());
+    bytes
+}
+
+pub fn plm_set_address(rom: &[u8], state_address: usize) -> Option<usize> {
+    let pointer = rom::read_u16(rom, state_address + PLM_SET_POINTER) as usize;
+    (pointer >= 0x8000).then(|| rom::snes_to_pc(ROOM_BANK | pointer))
+}
+
+pub fn set_plm_set_address(rom: &mut [u8], state_address: usize, address: usize) {
+    let pointer = rom::pc_to_snes(address) as u16;
+    rom::write_u16(rom, state_address + PLM_SET_POINTER, pointer);
+}
+
+/// Returns the PLM set, and its size in the ROM.
+pub fn load_plm_set(rom: &[u8], address: usize) -> (Vec<Plm>, usize) {
+    let plm_set: Vec<Plm> = (address..)
+        .step_by(PLM_SIZE)
+        .take_while(|address| rom::read_u16(rom, *address) != LIST_TERMINATOR)
+        .map(|address| Plm::from_bytes(&rom[address..address + PLM_SIZE]))
+        .collect();
+
+    let size = plm_set.len() * PLM_SIZE + 2;
+    (plm_set, size)
+}
@@ -0,0 +1,43 @@
+use crate::rom::{self, ROOM_BANK};
+
+pub const RED: u8 = 0; // Hides the screen.
+pub const BLUE: u8 = 1; // Shows the screen, but not the bottom rows of blocks.
+pub const GREEN: u8 = 2; // Shows the whole screen.
+
+pub const SCROLL_NAMES: [&str; 3] = ["Red", "Blue", "Green"];
+
+// Offset of the scroll pointer in the state header.
+const SCROLLS_POINTER: usize = 14;
+
+// Pointers used instead of scroll data, when all the screens share a scroll value.
+const ALL_BLUE: u16 = 0x0000;
+const ALL_GREEN: u16 = 0x0001;
+
+/// The next scroll value, when cycling through them.
+pub fn next_scroll(scroll: u8) -> u8 {
+    (scroll + 1) % SCROLL_NAMES.len() as u8
+}
+
+pub fn scrolls_address(rom: &[u8], state_address: usize) -> Option<usize> {
+    let pointer = rom::read_u16(rom, state_address + SCROLLS_POINTER) as usize;
+    (pointer >= 0x8000).then(|| rom::snes_to_pc(ROOM_BANK | pointer))
+}
+
+/// The scroll value of all the screens, for states without scroll data.
+pub fn fixed_scroll(rom: &[u8], state_address: usize) -> Option<u8> {
+    match rom::read_u16(rom, state_address + SCROLLS_POINTER) {
+        ALL_BLUE => Some(BLUE),
+        ALL_GREEN => Some(GREEN),
+        _ => None,
+    }
+}
+
+pub fn set_scrolls_address(rom: &mut [u8], state_address: usize, address: usize) {
+    let pointer = rom::pc_to_snes(address) as u16;
+    rom::write_u16(rom, state_address + SCROLLS_POINTER, pointer);
+}
+
+/// The scroll value of each screen of the room, row by row.
+pub fn load_scrolls(rom: &[u8], address: usize, screens: usize) -> Vec<u8> {
+    rom[address..address + screens].to_vec()
+}
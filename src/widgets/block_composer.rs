@@ -0,0 +1,142 @@
+use eframe::egui::{DragValue, Grid, Ui};
+use zen::super_metroid::tile_table::{Tile8, TileTable};
+
+const CORNER_NAMES: [&str; 4] = ["Top left", "Top right", "Bottom left", "Bottom right"];
+
+/// One of the four 8x8 tiles making up a 16x16 block of a tile table.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub struct TileEntry {
+    pub tile: u16, // Tile number in the graphics.
+    pub sub_palette: u8,
+    pub priority: bool,
+    pub x_flip: bool,
+    pub y_flip: bool,
+}
+
+impl From<&Tile8> for TileEntry {
+    fn from(tile: &Tile8) -> Self {
+        Self {
+            tile: tile.gfx_index,
+            sub_palette: tile.sub_palette,
+            priority: tile.priority,
+            x_flip: tile.x_flip,
+            y_flip: tile.y_flip,
+        }
+    }
+}
+
+impl TileEntry {
+    fn write(&self, tile: &mut Tile8) {
+        tile.gfx_index = self.tile;
+        tile.sub_palette = self.sub_palette;
+        tile.priority = self.priority;
+        tile.x_flip = self.x_flip;
+        tile.y_flip = self.y_flip;
+    }
+}
+
+/// The tiles of a block, in the order of `CORNER_NAMES`.
+pub fn read_block_entries(tile_table: &TileTable, block: usize) -> [TileEntry; 4] {
+    let tile16 = &tile_table.tiles[block];
+    [
+        (&tile16.top_left).into(),
+        (&tile16.top_right).into(),
+        (&tile16.bottom_left).into(),
+        (&tile16.bottom_right).into(),
+    ]
+}
+
+pub fn write_block_entries(tile_table: &mut TileTable, block: usize, entries: &[TileEntry; 4]) {
+    let tile16 = &mut tile_table.tiles[block];
+    entries[0].write(&mut tile16.top_left);
+    entries[1].write(&mut tile16.top_right);
+    entries[2].write(&mut tile16.bottom_left);
+    entries[3].write(&mut tile16.bottom_right);
+}
+
+/// Edits the 8x8 tiles of the block selected in the tile table.
+#[derive(Default)]
+pub struct BlockComposer {
+    block: Option<usize>,
+    corner: usize, // Tile of the block being edited.
+}
+
+impl BlockComposer {
+    /// Returns the new tiles of the block when they change.
+    pub fn ui(&mut self, ui: &mut Ui, tile_table: &TileTable) -> Option<(usize, [TileEntry; 4])> {
+        let Some(block) = self.block.filter(|block| *block < tile_table.tiles.len()) else {
+            ui.label("Right click a block in the tile table");
+            return None;
+        };
+
+        ui.label(format!("Block {:#05x}", block));
+        let entries = read_block_entries(tile_table, block);
+
+        Grid::new("BlockComposerCorners")
+            .num_columns(2)
+            .show(ui, |ui| {
+                for (corner, entry) in entries.iter().enumerate() {
+                    let text = format!("{:#05x}", entry.tile);
+                    ui.selectable_value(&mut self.corner, corner, text)
+                        .on_hover_text(CORNER_NAMES[corner]);
+                    if corner % 2 == 1 {
+                        ui.end_row();
+                    }
+                }
+            });
+
+        let mut new = entries;
+        let entry = &mut new[self.corner];
+        Grid::new("BlockComposerTile")
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label("Tile");
+                ui.add(
+                    DragValue::new(&mut entry.tile)
+                        .clamp_range(0..=0x3FF)
+                        .hexadecimal(3, false, true),
+                )
+                .on_hover_text("Or click a tile in the graphics with the tile picker");
+                ui.end_row();
+
+                ui.label("Sub palette");
+                ui.add(DragValue::new(&mut entry.sub_palette).clamp_range(0..=7));
+                ui.end_row();
+
+                ui.label("Priority");
+                ui.checkbox(&mut entry.priority, "");
+                ui.end_row();
+
+                ui.label("Flip");
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut entry.x_flip, "X");
+                    ui.checkbox(&mut entry.y_flip, "Y");
+                });
+                ui.end_row();
+            });
+
+        (new != entries).then_some((block, new))
+    }
+
+    /// Assign a tile of the graphics to the tile being edited.
+    pub fn pick_tile(
+        &self,
+        tile_table: &TileTable,
+        tile: usize,
+    ) -> Option<(usize, [TileEntry; 4])> {
+        let block = self.block.filter(|block| *block < tile_table.tiles.len())?;
+
+        let mut entries = read_block_entries(tile_table, block);
+        entries[self.corner].tile = tile as u16;
+        Some((block, entries))
+    }
+
+    pub fn corner(&self) -> usize {
+        self.corner
+    }
+
+    pub fn set_block(&mut self, block: usize) {
+        self.block = Some(block);
+    }
+}
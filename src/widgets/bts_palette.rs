@@ -0,0 +1,99 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{Arc, Mutex},
+};
+
+use eframe::{
+    egui::{CollapsingHeader, ImageButton, TextureOptions, Ui},
+    epaint::{ColorImage, TextureHandle, Vec2},
+};
+
+use crate::assets::BtsTile;
+
+const ICON_SIZE: Vec2 = Vec2 { x: 24.0, y: 24.0 };
+
+pub struct BtsPalette {
+    bts_icons: Arc<Mutex<HashMap<BtsTile, ColorImage>>>,
+    textures: HashMap<BtsTile, TextureHandle>,
+    selected: Option<BtsTile>,
+}
+
+impl BtsPalette {
+    pub fn new(bts_icons: Arc<Mutex<HashMap<BtsTile, ColorImage>>>) -> Self {
+        Self {
+            bts_icons,
+            textures: HashMap::new(),
+            selected: None,
+        }
+    }
+}
+
+impl BtsPalette {
+    /// Draw the BTS icons grouped by block type. Returns the new selection when it changes.
+    pub fn ui(&mut self, ui: &mut Ui) -> Option<Option<BtsTile>> {
+        self.load_textures(ui);
+
+        // Group the icons by block type, sorted by their BTS value.
+        let mut groups: BTreeMap<u8, Vec<BtsTile>> = BTreeMap::new();
+        for bts_tile in self.textures.keys() {
+            groups
+                .entry(bts_tile.block_type as u8)
+                .or_default()
+                .push(*bts_tile);
+        }
+
+        let mut new_selection = None;
+        for (block_type, mut bts_tiles) in groups {
+            bts_tiles.sort_by_key(|bts_tile| bts_tile.bts_block);
+
+            CollapsingHeader::new(format!("{:X}: {:?}", block_type, bts_tiles[0].block_type))
+                .default_open(true)
+                .show(ui, |ui| {
+                    ui.horizontal_wrapped(|ui| {
+                        for bts_tile in bts_tiles {
+                            let selected = self.selected == Some(bts_tile);
+                            let button =
+                                ImageButton::new((self.textures[&bts_tile].id(), ICON_SIZE))
+                                    .selected(selected);
+
+                            if ui
+                                .add(button)
+                                .on_hover_text(format!("BTS: {:#04x}", bts_tile.bts_block))
+                                .clicked()
+                            {
+                                self.selected = (!selected).then_some(bts_tile);
+                                new_selection = Some(self.selected);
+                            }
+                        }
+                    });
+                });
+        }
+
+        new_selection
+    }
+
+    pub fn unselect(&mut self) {
+        self.selected = None;
+    }
+
+    // Icons are loaded asynchronously on web, so keep uploading the ones still missing.
+    fn load_textures(&mut self, ui: &Ui) {
+        let bts_icons = self.bts_icons.lock().unwrap();
+        if bts_icons.len() == self.textures.len() {
+            return;
+        }
+
+        for (bts_tile, bts_icon) in bts_icons.iter() {
+            self.textures.entry(*bts_tile).or_insert_with(|| {
+                ui.ctx().load_texture(
+                    format!(
+                        "BtsPalette_{:?}_{:x}",
+                        bts_tile.block_type, bts_tile.bts_block
+                    ),
+                    bts_icon.clone(),
+                    TextureOptions::NEAREST,
+                )
+            });
+        }
+    }
+}
@@ -0,0 +1,122 @@
+use eframe::egui::{ComboBox, DragValue, Grid, Ui};
+
+use crate::room::door::{Door, CLOSING_CAP, DIRECTIONS, DIRECTION_MASK};
+
+pub enum DoorListCommand {
+    Edited {
+        address: usize,
+        old: Door,
+        new: Door,
+    },
+    Goto(usize), // Room address of the destination.
+    None,
+}
+
+#[derive(Default)]
+pub struct DoorList {
+    selected: Option<usize>,
+}
+
+impl DoorList {
+    pub fn ui(&mut self, ui: &mut Ui, doors: &[(usize, Door)]) -> DoorListCommand {
+        if doors.is_empty() {
+            ui.label("No doors");
+            return DoorListCommand::None;
+        }
+
+        for (index, (_, door)) in doors.iter().enumerate() {
+            let text = format!(
+                "{}: {:04x} ({})",
+                index,
+                door.destination_room,
+                DIRECTIONS[(door.direction & DIRECTION_MASK) as usize]
+            );
+            if ui
+                .selectable_label(self.selected == Some(index), text)
+                .clicked()
+            {
+                self.selected = (self.selected != Some(index)).then_some(index);
+            }
+        }
+
+        let Some((address, door)) = self.selected.and_then(|index| doors.get(index)) else {
+            return DoorListCommand::None;
+        };
+
+        ui.separator();
+        let mut command = DoorListCommand::None;
+        let mut new = *door;
+        Grid::new("DoorGrid").num_columns(2).show(ui, |ui| {
+            ui.label("Destination");
+            ui.horizontal(|ui| {
+                ui.add(DragValue::new(&mut new.destination_room).hexadecimal(4, false, true));
+                if ui.button("Go").clicked() {
+                    command = DoorListCommand::Goto(door.destination_room_address());
+                }
+            });
+            ui.end_row();
+
+            ui.label("Direction");
+            let mut direction = new.direction & DIRECTION_MASK;
+            let mut closing_cap = new.direction & CLOSING_CAP != 0;
+            ComboBox::from_id_source("DoorDirection")
+                .selected_text(DIRECTIONS[direction as usize])
+                .show_ui(ui, |ui| {
+                    for (value, name) in DIRECTIONS.iter().enumerate() {
+                        ui.selectable_value(&mut direction, value as u8, *name);
+                    }
+                });
+            ui.end_row();
+
+            ui.label("Closing cap");
+            ui.checkbox(&mut closing_cap, "");
+            new.direction = direction | if closing_cap { CLOSING_CAP } else { 0 };
+            ui.end_row();
+
+            ui.label("Cap X/Y");
+            ui.horizontal(|ui| {
+                ui.add(DragValue::new(&mut new.cap_x));
+                ui.add(DragValue::new(&mut new.cap_y));
+            });
+            ui.end_row();
+
+            ui.label("Screen X/Y");
+            ui.horizontal(|ui| {
+                ui.add(DragValue::new(&mut new.screen_x));
+                ui.add(DragValue::new(&mut new.screen_y));
+            });
+            ui.end_row();
+
+            ui.label("Spawn distance");
+            ui.add(DragValue::new(&mut new.spawn_distance).hexadecimal(4, false, true));
+            ui.end_row();
+
+            ui.label("ASM pointer");
+            ui.add(DragValue::new(&mut new.asm_pointer).hexadecimal(4, false, true));
+            ui.end_row();
+
+            ui.label("Flags");
+            ui.add(DragValue::new(&mut new.bitflag).hexadecimal(2, false, true));
+            ui.end_row();
+        });
+
+        if new != *door {
+            command = DoorListCommand::Edited {
+                address: *address,
+                old: *door,
+                new,
+            };
+        }
+
+        command
+    }
+
+    /// Index of the selected door, as used by the BTS of its door blocks.
+    pub fn selected(&self) -> Option<usize> {
+        self.selected
+    }
+
+    pub fn unselect(&mut self) {
+        self.selected = None;
+    }
+}
@@ -0,0 +1,358 @@
+use eframe::{
+    egui::{Button, DragValue, Grid, PointerButton, Response, Ui},
+    emath::Align2,
+    epaint::{Color32, FontId, Pos2, Rect, Stroke, Vec2},
+};
+
+use crate::{
+    history::ListEdit,
+    room::{Enemy, EnemyGfx, EnemyPopulation, ENEMY_IDS},
+};
+
+const MARKER_SIZE: f32 = 16.0; // In level pixels.
+const MARKER_COLOR: Color32 = Color32::from_rgb(255, 64, 64);
+const MARKER_FILL: Color32 = Color32::from_rgba_premultiplied(64, 16, 16, 64);
+const SELECTED_COLOR: Color32 = Color32::YELLOW;
+const SELECTED_FILL: Color32 = Color32::from_rgba_premultiplied(64, 64, 0, 64);
+
+pub enum EnemyCommand {
+    Population(ListEdit, EnemyPopulation),
+    EnemySet(ListEdit, Vec<EnemyGfx>),
+    None,
+}
+
+/// An enemy being moved, with its position and the pointer offset from it, in level pixels.
+#[derive(Clone, Copy)]
+struct Dragging {
+    index: usize,
+    offset: Vec2,
+    position: Pos2,
+}
+
+#[derive(Default)]
+pub struct EnemyEditor {
+    selected: Option<usize>,
+    dragging: Option<Dragging>,
+    new_id: u16, // ID of the enemies added with nothing selected to copy.
+}
+
+impl EnemyEditor {
+    /// Draw the enemies over the level, of `level_size` in pixels, and move them by dragging
+    /// when `interactive`. Uses the response of the level editor, as it is drawn on top of it.
+    pub fn overlay(
+        &mut self,
+        ui: &Ui,
+        response: &Response,
+        widget_rect: Rect,
+        level_size: Vec2,
+        population: &EnemyPopulation,
+        interactive: bool,
+    ) -> EnemyCommand {
+        if level_size.x == 0.0 {
+            return EnemyCommand::None;
+        }
+        let scale = widget_rect.width() / level_size.x;
+        let to_level = |position: Pos2| ((position - widget_rect.min) / scale).to_pos2();
+
+        let command = if interactive {
+            self.drag_enemies(ui, response, to_level, level_size, population)
+        } else {
+            self.dragging = None;
+            EnemyCommand::None
+        };
+
+        let painter = ui.painter_at(widget_rect);
+        for (index, enemy) in population.enemies.iter().enumerate() {
+            let position = match self.dragging {
+                Some(dragging) if dragging.index == index => dragging.position,
+                _ => Self::position(enemy),
+            };
+            let (color, fill) = if self.selected == Some(index) {
+                (SELECTED_COLOR, SELECTED_FILL)
+            } else {
+                (MARKER_COLOR, MARKER_FILL)
+            };
+
+            let marker = Rect::from_center_size(
+                widget_rect.min + position.to_vec2() * scale,
+                Vec2::splat(MARKER_SIZE * scale),
+            );
+            painter.rect(marker, 2.0, fill, Stroke::new(2.0, color));
+            painter.text(
+                marker.center_top(),
+                Align2::CENTER_BOTTOM,
+                format!("{}: {:04X}", index, enemy.id),
+                FontId::proportional(12.0),
+                color,
+            );
+        }
+
+        command
+    }
+
+    fn drag_enemies(
+        &mut self,
+        ui: &Ui,
+        response: &Response,
+        to_level: impl Fn(Pos2) -> Pos2,
+        level_size: Vec2,
+        population: &EnemyPopulation,
+    ) -> EnemyCommand {
+        // The population may change while dragging, like when undoing.
+        if matches!(self.dragging, Some(dragging) if dragging.index >= population.enemies.len()) {
+            self.dragging = None;
+        }
+
+        if response.drag_started_by(PointerButton::Primary) {
+            // Start from where the button was pressed, as the pointer already moved.
+            let press_origin = ui.input(|i| i.pointer.press_origin()).map(&to_level);
+            self.dragging = press_origin.and_then(|pointer| {
+                let index = Self::enemy_at(population, pointer)?;
+                let position = Self::position(&population.enemies[index]);
+                Some(Dragging {
+                    index,
+                    offset: pointer - position,
+                    position,
+                })
+            });
+            if let Some(dragging) = self.dragging {
+                self.selected = Some(dragging.index);
+            }
+        } else if response.dragged_by(PointerButton::Primary) {
+            let pointer = response.interact_pointer_pos().map(&to_level);
+            if let (Some(dragging), Some(pointer)) = (self.dragging.as_mut(), pointer) {
+                dragging.position = (pointer - dragging.offset)
+                    .clamp(Pos2::ZERO, level_size.to_pos2())
+                    .round();
+            }
+        } else if response.drag_released_by(PointerButton::Primary) {
+            if let Some(dragging) = self.dragging.take() {
+                let mut new = population.clone();
+                let enemy = &mut new.enemies[dragging.index];
+                enemy.x = dragging.position.x as u16;
+                enemy.y = dragging.position.y as u16;
+
+                if new != *population {
+                    return EnemyCommand::Population(ListEdit::Moved(dragging.index), new);
+                }
+            }
+        } else if response.clicked() {
+            let pointer = response.interact_pointer_pos().map(&to_level);
+            self.selected = pointer.and_then(|pointer| Self::enemy_at(population, pointer));
+        }
+
+        EnemyCommand::None
+    }
+
+    /// Edit the enemy population and enemy set of a state.
+    pub fn ui(
+        &mut self,
+        ui: &mut Ui,
+        population: &EnemyPopulation,
+        enemy_set: Option<&[EnemyGfx]>,
+    ) -> EnemyCommand {
+        if matches!(self.selected, Some(index) if index >= population.enemies.len()) {
+            self.selected = None;
+        }
+
+        let mut command = self.population_ui(ui, population);
+
+        ui.separator();
+        ui.label("Enemy set");
+        match enemy_set {
+            Some(enemy_set) => {
+                if let Some((edit, new)) = self.enemy_set_ui(ui, population, enemy_set) {
+                    command = EnemyCommand::EnemySet(edit, new);
+                }
+            }
+            None => {
+                ui.label("No enemy set");
+            }
+        }
+
+        command
+    }
+
+    fn population_ui(&mut self, ui: &mut Ui, population: &EnemyPopulation) -> EnemyCommand {
+        for (index, enemy) in population.enemies.iter().enumerate() {
+            let text = format!("{}: {:04X} ({}, {})", index, enemy.id, enemy.x, enemy.y);
+            if ui
+                .selectable_label(self.selected == Some(index), text)
+                .clicked()
+            {
+                self.selected = (self.selected != Some(index)).then_some(index);
+            }
+        }
+
+        let mut new = population.clone();
+        let mut edit = None;
+        ui.horizontal(|ui| {
+            // New enemies copy the selected one, or need a valid ID.
+            if self.selected.is_none() {
+                ui.add(DragValue::new(&mut self.new_id).hexadecimal(4, false, true))
+                    .on_hover_text("ID of the enemy to add");
+            }
+            let can_add = self.selected.is_some() || ENEMY_IDS.contains(&self.new_id);
+            if ui.add_enabled(can_add, Button::new("Add")).clicked() {
+                // Copy the selected enemy, next to it, as a starting point.
+                let mut enemy = match self.selected {
+                    Some(index) => population.enemies[index],
+                    None => Enemy {
+                        id: self.new_id,
+                        ..Default::default()
+                    },
+                };
+                enemy.x = enemy.x.saturating_add(MARKER_SIZE as u16);
+
+                new.enemies.push(enemy);
+                self.selected = Some(new.enemies.len() - 1);
+                edit = Some(ListEdit::Added);
+            }
+            if ui
+                .add_enabled(self.selected.is_some(), Button::new("Remove"))
+                .clicked()
+            {
+                new.enemies.remove(self.selected.take().unwrap());
+                edit = Some(ListEdit::Removed);
+            }
+        });
+
+        Grid::new("EnemyPopulationGrid")
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label("Kill count");
+                if ui.add(DragValue::new(&mut new.kill_count)).changed() {
+                    edit = Some(ListEdit::Count);
+                }
+                ui.end_row();
+
+                let Some(index) = self.selected else {return};
+                if Self::enemy_grid(ui, &mut new.enemies[index]) {
+                    edit = Some(ListEdit::Values(index));
+                }
+            });
+
+        match edit {
+            Some(edit) if new != *population => EnemyCommand::Population(edit, new),
+            _ => EnemyCommand::None,
+        }
+    }
+
+    // Returns whether the enemy changed.
+    fn enemy_grid(ui: &mut Ui, enemy: &mut Enemy) -> bool {
+        let original = *enemy;
+
+        ui.label("ID");
+        ui.add(
+            DragValue::new(&mut enemy.id)
+                .hexadecimal(4, false, true)
+                .clamp_range(ENEMY_IDS),
+        );
+        ui.end_row();
+
+        ui.label("X/Y");
+        ui.horizontal(|ui| {
+            ui.add(DragValue::new(&mut enemy.x));
+            ui.add(DragValue::new(&mut enemy.y));
+        });
+        ui.end_row();
+
+        ui.label("Initial parameter");
+        ui.add(DragValue::new(&mut enemy.initial_parameter).hexadecimal(4, false, true));
+        ui.end_row();
+
+        ui.label("Properties");
+        ui.add(DragValue::new(&mut enemy.properties).hexadecimal(4, false, true));
+        ui.end_row();
+
+        ui.label("Extra properties");
+        ui.add(DragValue::new(&mut enemy.extra_properties).hexadecimal(4, false, true));
+        ui.end_row();
+
+        ui.label("Parameter 1");
+        ui.add(DragValue::new(&mut enemy.parameter1).hexadecimal(4, false, true));
+        ui.end_row();
+
+        ui.label("Parameter 2");
+        ui.add(DragValue::new(&mut enemy.parameter2).hexadecimal(4, false, true));
+        ui.end_row();
+
+        *enemy != original
+    }
+
+    fn enemy_set_ui(
+        &mut self,
+        ui: &mut Ui,
+        population: &EnemyPopulation,
+        enemy_set: &[EnemyGfx],
+    ) -> Option<(ListEdit, Vec<EnemyGfx>)> {
+        let mut new = enemy_set.to_vec();
+        let mut edit = None;
+
+        Grid::new("EnemySetGrid").num_columns(3).show(ui, |ui| {
+            ui.label("ID");
+            ui.label("Palette");
+            ui.end_row();
+
+            let mut removed = None;
+            for (index, enemy_gfx) in new.iter_mut().enumerate() {
+                let id = ui.add(
+                    DragValue::new(&mut enemy_gfx.id)
+                        .hexadecimal(4, false, true)
+                        .clamp_range(ENEMY_IDS),
+                );
+                let palette =
+                    ui.add(DragValue::new(&mut enemy_gfx.palette).hexadecimal(4, false, true));
+                if id.changed() || palette.changed() {
+                    edit = Some(ListEdit::Values(index));
+                }
+                if ui.button("Remove").clicked() {
+                    removed = Some(index);
+                }
+                ui.end_row();
+            }
+
+            if let Some(index) = removed {
+                new.remove(index);
+                edit = Some(ListEdit::Removed);
+            }
+        });
+
+        // Load the graphics of the selected enemy, else of the ID to add.
+        let id = self
+            .selected
+            .map(|index| population.enemies[index].id)
+            .unwrap_or(self.new_id);
+        if ui
+            .add_enabled(ENEMY_IDS.contains(&id), Button::new("Add"))
+            .clicked()
+        {
+            new.push(EnemyGfx { id, palette: 0 });
+            edit = Some(ListEdit::Added);
+        }
+
+        edit.filter(|_| new != enemy_set).map(|edit| (edit, new))
+    }
+
+    /// Index of the selected enemy, in the enemy population.
+    pub fn selected(&self) -> Option<usize> {
+        self.selected
+    }
+
+    pub fn unselect(&mut self) {
+        self.selected = None;
+        self.dragging = None;
+    }
+
+    fn position(enemy: &Enemy) -> Pos2 {
+        Pos2::new(enemy.x as f32, enemy.y as f32)
+    }
+
+    // The last enemy drawn is on top, so search from the end.
+    fn enemy_at(population: &EnemyPopulation, position: Pos2) -> Option<usize> {
+        population.enemies.iter().rposition(|enemy| {
+            Rect::from_center_size(Self::position(enemy), Vec2::splat(MARKER_SIZE))
+                .contains(position)
+        })
+    }
+}
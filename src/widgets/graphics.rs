@@ -1,14 +1,53 @@
-use eframe::egui::{Context, Sense, Ui};
-use zen::{graphics::IndexedColor, graphics::Palette};
-// use zen::graphics::gfx::GFX_TILE_WIDTH;
+use eframe::{
+    egui::{Context, PointerButton, Sense, Ui},
+    epaint::{Color32, Pos2, Rect, Stroke, Vec2},
+};
+use zen::{
+    graphics::{
+        gfx::GFX_TILE_WIDTH,
+        palette::{COLORS_BY_SUB_PALETTE, NUMBER_OF_SUB_PALETTES},
+        IndexedColor, Palette, Rgb888,
+    },
+    super_metroid::tile_table::TileTable,
+};
 
-use super::helpers::{indexed_texture::IndexedTexture, zoom_area::ZoomArea};
+use crate::history::PixelEdit;
 
-// const SELECTION_SIZE: [f32; 2] = [GFX_TILE_WIDTH as f32, GFX_TILE_WIDTH as f32];
+use super::{
+    block_composer::read_block_entries,
+    helpers::{indexed_texture::IndexedTexture, zoom_area::ZoomArea},
+};
+
+const SWATCH_SIZE: Vec2 = Vec2 { x: 16.0, y: 16.0 };
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GraphicsTool {
+    #[default]
+    Pencil,
+    Eyedropper,
+    TilePicker,
+}
+
+pub enum GraphicsCommand {
+    Draw(usize, Vec<PixelEdit>), // Pixels drawn, with the stroke they belong to.
+    TilePicked(usize),
+    Import,
+    Export,
+    None,
+}
 
 pub struct GraphicsEditor {
     zoomable_area: ZoomArea,
     texture: IndexedTexture,
+    tool: GraphicsTool,
+    color: usize,       // Color index being drawn, in the sub palette.
+    sub_palette: usize, // Sub palette shown by the color chooser, and used to color the sheet.
+    stroke: usize,      // Incremented for every new stroke, so its edits can be merged.
+    // Last pixel drawn by the stroke, joined to the next one.
+    last_pixel: Option<[usize; 2]>,
+    // Color each tile with the sub palette the tile table uses most with it, instead.
+    auto_sub_palette: bool,
+    tile_sub_palettes: Vec<Option<usize>>,
 }
 
 impl Default for GraphicsEditor {
@@ -16,16 +55,262 @@ impl Default for GraphicsEditor {
         Self {
             zoomable_area: ZoomArea::default(),
             texture: IndexedTexture::new("GraphicsEditor".to_string()),
+            tool: GraphicsTool::default(),
+            color: 1,
+            sub_palette: 0,
+            stroke: 0,
+            last_pixel: None,
+            auto_sub_palette: false,
+            tile_sub_palettes: Vec::default(),
         }
     }
 }
 
 impl GraphicsEditor {
-    pub fn ui(&mut self, ui: &mut Ui) {
-        let (widget_rect, _) =
+    pub fn ui(&mut self, ui: &mut Ui, palette: &Palette) -> GraphicsCommand {
+        let toolbar_command = self.toolbar(ui, palette);
+
+        let (widget_rect, response) =
             self.zoomable_area
-                .create(ui, self.texture.size(), Sense::focusable_noninteractive());
+                .create(ui, self.texture.size(), Sense::click_and_drag());
         self.texture.ui(ui, widget_rect);
+        if let Some(command) = toolbar_command {
+            return command;
+        }
+
+        let Some(pixel) = response
+            .interact_pointer_pos()
+            .and_then(|pointer| self.pixel_at(widget_rect, pointer))
+        else {
+            return GraphicsCommand::None;
+        };
+
+        // The secondary button always picks the color, whatever the tool.
+        if response.clicked_by(PointerButton::Secondary)
+            || response.dragged_by(PointerButton::Secondary)
+        {
+            self.pick_color(pixel);
+            return GraphicsCommand::None;
+        }
+
+        if !(response.clicked() || response.dragged_by(PointerButton::Primary)) {
+            return GraphicsCommand::None;
+        }
+        if response.drag_started() || response.clicked() {
+            self.stroke += 1;
+            self.last_pixel = None;
+        }
+
+        match self.tool {
+            GraphicsTool::Pencil => {
+                let edits = self.draw_line(pixel, palette);
+                if edits.is_empty() {
+                    GraphicsCommand::None
+                } else {
+                    GraphicsCommand::Draw(self.stroke, edits)
+                }
+            }
+            GraphicsTool::Eyedropper => {
+                self.pick_color(pixel);
+                GraphicsCommand::None
+            }
+            GraphicsTool::TilePicker if response.clicked() => {
+                GraphicsCommand::TilePicked(self.tile_at(pixel))
+            }
+            GraphicsTool::TilePicker => GraphicsCommand::None,
+        }
+    }
+
+    fn toolbar(&mut self, ui: &mut Ui, palette: &Palette) -> Option<GraphicsCommand> {
+        let mut command = None;
+        ui.horizontal(|ui| {
+            if ui.button("Import PNG").clicked() {
+                command = Some(GraphicsCommand::Import);
+            }
+            if ui.button("Export PNG").clicked() {
+                command = Some(GraphicsCommand::Export);
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.selectable_value(&mut self.tool, GraphicsTool::Pencil, "Pencil")
+                .on_hover_text("Draw with the chosen color");
+            ui.selectable_value(&mut self.tool, GraphicsTool::Eyedropper, "Eyedropper")
+                .on_hover_text("Pick a color from the graphics (right click)");
+            ui.selectable_value(&mut self.tool, GraphicsTool::TilePicker, "Tile picker")
+                .on_hover_text("Assign a tile to the block composer");
+        });
+
+        let mut recolor = false;
+        ui.horizontal_wrapped(|ui| {
+            ui.label("Sub palette");
+            for sub_palette in 0..NUMBER_OF_SUB_PALETTES {
+                recolor |= ui
+                    .selectable_value(&mut self.sub_palette, sub_palette, sub_palette.to_string())
+                    .clicked();
+            }
+            recolor |= ui
+                .checkbox(&mut self.auto_sub_palette, "Auto")
+                .on_hover_text("Color each tile with the sub palette the tile table uses most")
+                .changed();
+        });
+        if recolor {
+            self.apply_sub_palettes(palette);
+        }
+
+        ui.horizontal_wrapped(|ui| {
+            ui.spacing_mut().item_spacing = Vec2::splat(2.0);
+            for index in 0..COLORS_BY_SUB_PALETTE {
+                let (rect, response) = ui.allocate_exact_size(SWATCH_SIZE, Sense::click());
+                let color: Rgb888 = palette.sub_palettes[self.sub_palette].colors[index].into();
+
+                let stroke = if index == self.color {
+                    Stroke::new(2.0, Color32::WHITE)
+                } else {
+                    Stroke::new(1.0, Color32::BLACK)
+                };
+                ui.painter().rect(
+                    rect,
+                    0.0,
+                    Color32::from_rgb(color.r, color.g, color.b),
+                    stroke,
+                );
+
+                if response.on_hover_text(format!("Color {}", index)).clicked() {
+                    self.color = index;
+                }
+            }
+        });
+
+        command
+    }
+
+    fn pixel_at(&self, widget_rect: Rect, pointer: Pos2) -> Option<[usize; 2]> {
+        let size = self.texture.size();
+        if !widget_rect.contains(pointer) || size.x == 0.0 {
+            return None;
+        }
+
+        let pixel = ((pointer - widget_rect.min) / widget_rect.size() * size).floor();
+        Some([
+            (pixel.x as usize).min(size.x as usize - 1),
+            (pixel.y as usize).min(size.y as usize - 1),
+        ])
+    }
+
+    fn pick_color(&mut self, [x, y]: [usize; 2]) {
+        let width = self.texture.size().x as usize;
+        self.color = self.texture.indexed_colors[x + y * width].index;
+    }
+
+    fn tile_at(&self, [x, y]: [usize; 2]) -> usize {
+        let tiles_by_row = self.texture.size().x as usize / GFX_TILE_WIDTH;
+        (x / GFX_TILE_WIDTH) + (y / GFX_TILE_WIDTH) * tiles_by_row
+    }
+
+    /// Draw from the last pixel of the stroke to `pixel`, so fast pointer moves don't leave
+    /// gaps. Only the region drawn is uploaded.
+    fn draw_line(&mut self, pixel: [usize; 2], palette: &Palette) -> Vec<PixelEdit> {
+        let width = self.texture.size().x as usize;
+        let start = self.last_pixel.unwrap_or(pixel);
+        self.last_pixel = Some(pixel);
+
+        let [start_x, start_y] = start.map(|p| p as f32);
+        let [end_x, end_y] = pixel.map(|p| p as f32);
+        let steps = (end_x - start_x).abs().max((end_y - start_y).abs()) as usize;
+
+        let mut edits = Vec::new();
+        for step in 0..=steps {
+            let t = if steps == 0 {
+                1.0
+            } else {
+                step as f32 / steps as f32
+            };
+            let x = (start_x + (end_x - start_x) * t).round() as usize;
+            let y = (start_y + (end_y - start_y) * t).round() as usize;
+
+            let indexed_color = &mut self.texture.indexed_colors[x + y * width];
+            if indexed_color.index == self.color {
+                continue;
+            }
+            edits.push(PixelEdit {
+                position: [x, y],
+                old: indexed_color.index as u8,
+                new: self.color as u8,
+            });
+            indexed_color.index = self.color;
+        }
+
+        if !edits.is_empty() {
+            let min = [start[0].min(pixel[0]), start[1].min(pixel[1])];
+            let max = [start[0].max(pixel[0]), start[1].max(pixel[1])];
+            let size = [max[0] - min[0] + 1, max[1] - min[1] + 1];
+            self.texture.apply_region_colors(palette, min, size);
+        }
+
+        edits
+    }
+
+    /// Color every tile of the sheet with the chosen sub palette, or its most used one.
+    fn apply_sub_palettes(&mut self, palette: &Palette) {
+        let width = self.texture.size().x as usize;
+        if width == 0 {
+            return;
+        }
+
+        let tiles_by_row = width / GFX_TILE_WIDTH;
+        for (pixel, indexed_color) in self.texture.indexed_colors.iter_mut().enumerate() {
+            let [x, y] = [pixel % width, pixel / width];
+            let tile = (x / GFX_TILE_WIDTH) + (y / GFX_TILE_WIDTH) * tiles_by_row;
+
+            indexed_color.sub_palette = self
+                .tile_sub_palettes
+                .get(tile)
+                .copied()
+                .flatten()
+                .filter(|_| self.auto_sub_palette)
+                .unwrap_or(self.sub_palette);
+        }
+        self.texture.apply_colors(palette);
+    }
+
+    /// Count the sub palettes each tile is used with by `tile_table`, for the auto mode.
+    pub fn set_tile_sub_palettes(&mut self, tile_table: &TileTable, palette: &Palette) {
+        let tiles = self.texture.indexed_colors.len() / (GFX_TILE_WIDTH * GFX_TILE_WIDTH);
+        let mut counts = vec![[0; NUMBER_OF_SUB_PALETTES]; tiles];
+        for block in 0..tile_table.tiles.len() {
+            for entry in read_block_entries(tile_table, block) {
+                if let Some(count) = counts.get_mut(entry.tile as usize) {
+                    count[entry.sub_palette as usize % NUMBER_OF_SUB_PALETTES] += 1;
+                }
+            }
+        }
+
+        self.tile_sub_palettes = counts
+            .iter()
+            .map(|count| {
+                (0..NUMBER_OF_SUB_PALETTES)
+                    .filter(|sub_palette| count[*sub_palette] > 0)
+                    .max_by_key(|sub_palette| count[*sub_palette])
+            })
+            .collect();
+        self.apply_sub_palettes(palette);
+    }
+
+    pub fn sub_palette(&self) -> usize {
+        self.sub_palette
+    }
+
+    /// Start a stroke for edits made outside of the editor, like importing the graphics.
+    pub fn new_stroke(&mut self) -> usize {
+        self.stroke += 1;
+        self.stroke
+    }
+
+    pub fn set_sub_palette(&mut self, sub_palette: usize, palette: &Palette) {
+        self.sub_palette = sub_palette;
+        self.auto_sub_palette = false;
+        self.apply_sub_palettes(palette);
     }
 
     pub fn load_texture(
@@ -37,6 +322,7 @@ impl GraphicsEditor {
     ) {
         self.texture
             .load_colors(ctx, indexed_colors, palette, texture_size);
+        self.apply_sub_palettes(palette);
     }
 
     pub fn apply_colors(&mut self, palette: &Palette) {
@@ -1,6 +1,6 @@
 use eframe::{
     egui::{Context, Response, Ui},
-    epaint::{Pos2, Rect, Vec2},
+    epaint::{Pos2, Rect, TextureHandle, Vec2},
 };
 use zen::graphics::{IndexedColor, Palette};
 
@@ -15,11 +15,14 @@ pub struct Editor {
     texture_to_edit: IndexedTexture,
     selection_size: [f32; 2],
     selected_texture: IndexedTexture,
+    texture_visible: bool,
 }
 
 pub enum Command {
     Selection(Rect, Vec<IndexedColor>),
     Apply(Pos2),
+    Drag(Pos2),
+    Area(Rect),
 }
 
 impl Editor {
@@ -30,13 +33,16 @@ impl Editor {
             texture_to_edit: IndexedTexture::new(format!("Texture_To_Edit_{}", name)),
             selected_texture: IndexedTexture::new(format!("Selected_Texture_{}", name)),
             selection_size,
+            texture_visible: true,
         }
     }
 
     pub fn ui(&mut self, ui: &mut Ui) -> (Response, Rect, Option<Command>) {
         let (widget_rect, widget_response) = self.drag_area.create(ui, self.texture_to_edit.size());
 
-        self.texture_to_edit.ui(ui, widget_rect);
+        if self.texture_visible {
+            self.texture_to_edit.ui(ui, widget_rect);
+        }
 
         let Some(action) = self.selection.ui(ui, widget_rect, &widget_response) else {return (widget_response, widget_rect, None)};
         let command = match action {
@@ -49,18 +55,21 @@ impl Editor {
                 self.crop_selection(selection),
             )),
             Selectable::Clicked(position) => Some(Command::Apply(position)),
+            Selectable::AreaDragging(_, position) => Some(Command::Drag(position)),
+            Selectable::AreaSelected(area) => Some(Command::Area(area)),
             _ => None,
         };
 
         (widget_response, widget_rect, command)
     }
 
-    pub fn edit_texture(
+    /// Write an image into the texture, without uploading it.
+    /// Used to batch many writes, followed by a single `upload_texture`.
+    pub fn write_texture(
         &mut self,
         position: Pos2,
         selection_width: f32,
         indexed_image: &Vec<IndexedColor>,
-        palette: &Palette,
     ) {
         let click_pixel_position = [
             (position.x * self.selection_size[0]) as usize,
@@ -77,8 +86,6 @@ impl Editor {
                 + (index / selection_width_in_pixels) * screen_width_in_pixels;
             new_indexed_colors[x + y] = *indexed_color;
         }
-
-        self.texture_to_edit.apply_colors(palette);
     }
 
     pub fn crop_selection(&self, selection: Rect) -> Vec<IndexedColor> {
@@ -128,11 +135,38 @@ impl Editor {
             .load_colors(ctx, indexed_colors, palette, texture_size);
     }
 
+    /// Upload the texture being edited, leaving the selection as is.
+    pub fn upload_texture(&mut self, palette: &Palette) {
+        self.texture_to_edit.apply_colors(palette);
+    }
+
     pub fn apply_colors(&mut self, palette: &Palette) {
         self.texture_to_edit.apply_colors(palette);
         self.selected_texture.apply_colors(palette);
     }
 
+    pub fn texture(&self) -> Option<&TextureHandle> {
+        self.texture_to_edit.texture.texture.as_ref()
+    }
+
+    pub fn set_texture_visible(&mut self, visible: bool) {
+        self.texture_visible = visible;
+    }
+
+    pub fn set_area_painted(&mut self, painted: bool) {
+        self.selection.paint_area = painted;
+    }
+
+    pub fn set_transparent(&mut self, transparent: bool) {
+        self.texture_to_edit.transparent = transparent;
+        self.selected_texture.transparent = transparent;
+    }
+
+    /// Exchange the texture being edited with another one, keeping selection and zoom.
+    pub fn swap_texture(&mut self, texture: &mut IndexedTexture) {
+        std::mem::swap(&mut self.texture_to_edit, texture);
+    }
+
     pub fn clear_selection(&mut self) {
         self.selected_texture.texture.texture = None;
     }
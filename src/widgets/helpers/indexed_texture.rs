@@ -1,6 +1,6 @@
 use eframe::{
     egui::{Context, Ui},
-    epaint::{Rect, Vec2},
+    epaint::{Color32, Rect, Vec2},
 };
 use zen::graphics::{IndexedColor, Palette, Rgb888};
 
@@ -9,6 +9,7 @@ use super::texture::Texture;
 pub struct IndexedTexture {
     pub texture: Texture,
     pub indexed_colors: Vec<IndexedColor>,
+    pub transparent: bool, // Draw color index 0 as transparent, like the SNES does.
 }
 
 impl IndexedTexture {
@@ -16,6 +17,7 @@ impl IndexedTexture {
         Self {
             texture: Texture::new(name),
             indexed_colors: Vec::default(),
+            transparent: false,
         }
     }
 
@@ -40,6 +42,10 @@ impl IndexedTexture {
             })
             .collect();
         self.texture.load_colors(ctx, colors, texture_size);
+
+        if self.transparent {
+            self.apply_colors(palette);
+        }
     }
 
     pub fn apply_colors(&mut self, palette: &Palette) {
@@ -47,7 +53,38 @@ impl IndexedTexture {
             palette.sub_palettes[idx_color.sub_palette].colors[idx_color.index].into()
         });
 
-        self.texture.apply_colors(colors);
+        if self.transparent {
+            let alphas = self
+                .indexed_colors
+                .iter()
+                .map(|idx_color| if idx_color.index == 0 { 0 } else { 255 });
+            self.texture.apply_colors_with_alpha(colors.zip(alphas));
+        } else {
+            self.texture.apply_colors(colors);
+        }
+    }
+
+    /// Same as `apply_colors`, for the `size` pixels at `position` only.
+    pub fn apply_region_colors(
+        &mut self,
+        palette: &Palette,
+        position: [usize; 2],
+        size: [usize; 2],
+    ) {
+        let width = self.texture.size()[0] as usize;
+        let pixels = (0..size[0] * size[1]).map(|index| {
+            let [x, y] = [position[0] + index % size[0], position[1] + index / size[0]];
+            let idx_color = self.indexed_colors[x + y * width];
+            let color: Rgb888 =
+                palette.sub_palettes[idx_color.sub_palette].colors[idx_color.index].into();
+            let alpha = if self.transparent && idx_color.index == 0 {
+                0
+            } else {
+                255
+            };
+            Color32::from_rgba_unmultiplied(color.r, color.g, color.b, alpha)
+        });
+        self.texture.set_region(position, size, pixels);
     }
 
     pub fn crop(&self, rect: Rect) -> Vec<IndexedColor> {
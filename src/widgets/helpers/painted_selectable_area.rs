@@ -9,12 +9,14 @@ use super::selectable_area::{Selectable, SelectableArea};
 
 pub struct PaintedSelectableArea {
     selectable: SelectableArea,
+    pub paint_area: bool, // Paint the area being dragged with the primary button, off by default.
 }
 
 impl PaintedSelectableArea {
     pub fn new(area: [f32; 2], size: [f32; 2]) -> Self {
         Self {
             selectable: SelectableArea::new(area, size),
+            paint_area: false,
         }
     }
 
@@ -35,6 +37,12 @@ impl PaintedSelectableArea {
                 Selectable::SelectedHovering(_) => (),
                 Selectable::Selected(_) => (),
                 Selectable::Clicked(_) => (),
+                Selectable::AreaDragging(rect, _) => {
+                    if self.paint_area {
+                        self.paint_selection(ui, widget_rect, *rect)
+                    }
+                }
+                Selectable::AreaSelected(_) => (),
             }
         }
 
@@ -7,6 +7,8 @@ use eframe::{
 pub struct SelectableArea {
     area_by_selection: Rect,
     selection: Option<Rect>,
+    area_drag_start: Option<Pos2>,
+    area_drag_end: Pos2, // Last position dragged over, kept when the pointer leaves the widget.
 }
 
 pub enum Selectable {
@@ -15,6 +17,8 @@ pub enum Selectable {
     Dragging(Rect),
     Selected(Rect),
     Clicked(Pos2),
+    AreaDragging(Rect, Pos2), // Area on screen, and the position being dragged over.
+    AreaSelected(Rect),
 }
 
 impl SelectableArea {
@@ -22,6 +26,8 @@ impl SelectableArea {
         Self {
             area_by_selection: Self::sizes(area, size),
             selection: None,
+            area_drag_start: None,
+            area_drag_end: Pos2::ZERO,
         }
     }
 
@@ -29,6 +35,22 @@ impl SelectableArea {
         let transform_area_to_screen = RectTransform::from_to(self.area_by_selection, widget_rect);
         let transform_screen_to_area = transform_area_to_screen.inverse();
 
+        // Finish the area drag once the button is released, even outside of the widget.
+        if self.area_drag_start.is_some() {
+            if let Some(pointer) = widget_response.interact_pointer_pos() {
+                self.area_drag_end = (transform_screen_to_area * pointer)
+                    .floor()
+                    .clamp(Pos2::ZERO, self.area_by_selection.max - Vec2::splat(1.0));
+            }
+            if !widget_response.ctx.input(|i| i.pointer.primary_down()) {
+                let area_drag_start = self.area_drag_start.take().unwrap();
+                return Some(Selectable::AreaSelected(Self::make_selection_area(
+                    area_drag_start,
+                    self.area_drag_end,
+                )));
+            }
+        }
+
         widget_response.hover_pos().and_then(|hover_pos| {
             let pointer_selection = (transform_screen_to_area * hover_pos).floor();
             let selection_position = transform_area_to_screen * pointer_selection;
@@ -65,6 +87,23 @@ impl SelectableArea {
                     *selection = Self::make_selection_area(selection.min, pointer_selection);
                     Some(Selectable::Selected(*selection))
                 })
+            } else if widget_response.drag_started_by(eframe::egui::PointerButton::Primary) {
+                self.area_drag_start = Some(pointer_selection);
+                self.area_drag_end = pointer_selection;
+                Some(Selectable::AreaDragging(
+                    transform_area_to_screen.transform_rect(single_selection),
+                    pointer_selection,
+                ))
+            } else if widget_response.dragged_by(eframe::egui::PointerButton::Primary) {
+                self.area_drag_start.map(|area_drag_start| {
+                    Selectable::AreaDragging(
+                        transform_area_to_screen.transform_rect(Self::make_selection_area(
+                            area_drag_start,
+                            pointer_selection,
+                        )),
+                        pointer_selection,
+                    )
+                })
             } else if widget_response.clicked() {
                 Some(Selectable::Clicked(pointer_selection))
             } else {
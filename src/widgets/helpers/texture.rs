@@ -1,6 +1,6 @@
 use eframe::{
     egui::{Context, Image, Ui, TextureOptions},
-    epaint::{ColorImage, Rect, TextureHandle, Vec2},
+    epaint::{Color32, ColorImage, Rect, TextureHandle, Vec2},
 };
 use zen::graphics::Rgb888;
 
@@ -56,6 +56,41 @@ impl Texture {
         }
     }
 
+    pub fn apply_colors_with_alpha(&mut self, colors: impl Iterator<Item = (Rgb888, u8)>) {
+        let Some(image) = self.image.as_mut() else {return};
+        image
+            .pixels
+            .iter_mut()
+            .zip(colors)
+            .for_each(|(pixel, (color, alpha))| {
+                *pixel = Color32::from_rgba_unmultiplied(color.r, color.g, color.b, alpha);
+            });
+        if let Some(texture) = self.texture.as_mut() {
+            texture.set(image.clone(), TextureOptions::NEAREST);
+        }
+    }
+
+    /// Replace the pixels of the `size` region at `position`, uploading only that region.
+    pub fn set_region(
+        &mut self,
+        position: [usize; 2],
+        size: [usize; 2],
+        pixels: impl Iterator<Item = Color32>,
+    ) {
+        let Some(image) = self.image.as_mut() else {return};
+        let width = image.size[0];
+
+        let mut region = ColorImage::new(size, Color32::TRANSPARENT);
+        for (index, pixel) in pixels.take(size[0] * size[1]).enumerate() {
+            let [x, y] = [position[0] + index % size[0], position[1] + index / size[0]];
+            image.pixels[x + y * width] = pixel;
+            region.pixels[index] = pixel;
+        }
+        if let Some(texture) = self.texture.as_mut() {
+            texture.set_partial(position, region, TextureOptions::NEAREST);
+        }
+    }
+
     pub fn size(&self) -> Vec2 {
         let size = if let Some(image) = self.image.as_ref() {
             image.size
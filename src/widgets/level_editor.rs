@@ -1,35 +1,85 @@
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     sync::{Arc, Mutex},
 };
 
 use eframe::{
-    egui::{Context, Response, TextureOptions, Ui},
-    epaint::{ColorImage, Pos2, Rect},
+    egui::{Checkbox, Context, Response, TextureOptions, Ui},
+    epaint::{Color32, ColorImage, Pos2, Rect, Shape, ShapeIdx, Stroke as PaintStroke, Vec2},
 };
 use zen::{
-    graphics::{gfx::GFX_TILE_WIDTH, IndexedColor, Palette},
+    graphics::{gfx::GFX_TILE_WIDTH, IndexedColor, Palette, Rgb888},
     super_metroid::{
-        level_data::{Block, BtsBlock, LevelData},
+        level_data::{Block, BlockType, BtsBlock, LevelData},
         tile_table::BLOCK_SIZE,
     },
 };
 
-use crate::assets::BtsTile;
+use crate::{
+    assets::BtsTile,
+    history::{write_block, BlockEdit},
+    notifications,
+};
 
 use super::helpers::{
     editor::{Command, Editor},
+    indexed_texture::IndexedTexture,
     texture::Texture,
 };
 
 const SELECTION_SIZE: [f32; 2] = [GFX_TILE_WIDTH as f32, GFX_TILE_WIDTH as f32];
 
+// BTS bits mirroring a slope, as used by the slope icons in `assets`.
+const SLOPE_X_FLIP: BtsBlock = 0b01_0_00000;
+const SLOPE_Y_FLIP: BtsBlock = 0b10_0_00000;
+
 pub struct LevelEditor {
     pub editor: Editor,
+    inactive_layer: IndexedTexture, // The layer not being edited, the editor holds the active one.
+    active_layer: Layer,
+    has_layer2: bool,
+    show_layer1: bool,
+    show_layer2: bool,
     bts_layer: Texture,
     bts_icons: Arc<Mutex<HashMap<BtsTile, ColorImage>>>,
+    reported_icons: HashSet<BtsTile>, // Missing icons already reported since the ROM was loaded.
     draw_bts: bool,
     edit_selection: BlockSelection,
+    brush_flip: [bool; 2], // Whether the brush is mirrored horizontally and vertically.
+    bts_brush: Option<BtsTile>, // Paints only the block type and BTS, when selected.
+    tool: Tool,
+    stroke: Option<Stroke>,
+    snap_to_brush: bool, // Align the stamps of a stroke to the brush size.
+    highlighted_door: Option<usize>, // Door index, as stored in the BTS of door blocks.
+    show_enemies: bool,
+    show_plms: bool,
+    show_scrolls: bool,
+}
+
+/// Edits made while dragging the stamp tool, merged into a single edit on release.
+struct Stroke {
+    start: Pos2,
+    last_position: Pos2,
+    last_stamp: Option<Pos2>,
+    layer: Layer,
+    edits: Vec<BlockEdit>,
+}
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tool {
+    #[default]
+    Stamp,
+    Fill,
+    Rectangle,
+    Enemies, // Moves the enemies drawn over the level, leaving the blocks untouched.
+    Scrolls, // Cycles the scroll value of the screens, leaving the blocks untouched.
+}
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Layer {
+    #[default]
+    Layer1,
+    Layer2,
 }
 
 pub struct BlockSelection {
@@ -48,14 +98,105 @@ impl Default for BlockSelection {
     }
 }
 
+impl BlockSelection {
+    /// Block at the given position of the selection, in blocks.
+    pub fn block(&self, x: usize, y: usize) -> (Block, BtsBlock) {
+        // Blocks are stored column by column.
+        self.data[x * self.rect.height() as usize + y]
+    }
+
+    /// Colors of the block at the given position of the selection, in blocks.
+    pub fn block_colors(&self, x: usize, y: usize) -> Vec<IndexedColor> {
+        let width_in_pixels = self.rect.width() as usize * BLOCK_SIZE;
+        (0..BLOCK_SIZE)
+            .flat_map(move |row| {
+                let start = (y * BLOCK_SIZE + row) * width_in_pixels + x * BLOCK_SIZE;
+                self.indexed_colors[start..start + BLOCK_SIZE]
+                    .iter()
+                    .copied()
+            })
+            .collect()
+    }
+
+    pub fn flip_horizontally(&mut self) {
+        let width = self.rect.width() as usize;
+        let height = self.rect.height() as usize;
+
+        // Blocks are stored column by column, so reverse the order of the columns.
+        let mut data = Vec::with_capacity(self.data.len());
+        for column in self.data.chunks(height).rev() {
+            for (block, bts) in column {
+                let mut block = *block;
+                let mut bts = *bts;
+                block.x_flip = !block.x_flip;
+                if block.block_type == BlockType::Slope {
+                    bts ^= SLOPE_X_FLIP;
+                }
+                data.push((block, bts));
+            }
+        }
+        self.data = data;
+
+        self.indexed_colors = self
+            .indexed_colors
+            .chunks(width * BLOCK_SIZE)
+            .flat_map(|row| row.iter().rev().copied())
+            .collect();
+    }
+
+    pub fn flip_vertically(&mut self) {
+        let width = self.rect.width() as usize;
+        let height = self.rect.height() as usize;
+
+        for column in self.data.chunks_mut(height) {
+            column.reverse();
+            for (block, bts) in column {
+                block.y_flip = !block.y_flip;
+                if block.block_type == BlockType::Slope {
+                    *bts ^= SLOPE_Y_FLIP;
+                }
+            }
+        }
+
+        self.indexed_colors = self
+            .indexed_colors
+            .chunks(width * BLOCK_SIZE)
+            .rev()
+            .flatten()
+            .copied()
+            .collect();
+    }
+}
+
 impl LevelEditor {
     pub fn new(bts_icons: Arc<Mutex<HashMap<BtsTile, ColorImage>>>) -> Self {
+        let mut editor = Editor::new("Level", SELECTION_SIZE);
+        editor.set_transparent(true);
+
+        let mut inactive_layer = IndexedTexture::new("InactiveLayer_LevelEditor".to_string());
+        inactive_layer.transparent = true;
+
         Self {
-            editor: Editor::new("Level", SELECTION_SIZE),
+            editor,
+            inactive_layer,
+            active_layer: Layer::default(),
+            has_layer2: false,
+            show_layer1: true,
+            show_layer2: true,
             bts_layer: Texture::new("BtsLayer_LevelEditor".to_string()),
             bts_icons,
+            reported_icons: HashSet::new(),
             draw_bts: true,
             edit_selection: BlockSelection::default(),
+            brush_flip: [false, false],
+            bts_brush: None,
+            tool: Tool::default(),
+            stroke: None,
+            snap_to_brush: false,
+            highlighted_door: None,
+            show_enemies: true,
+            show_plms: true,
+            show_scrolls: false,
         }
     }
 }
@@ -66,9 +207,14 @@ impl LevelEditor {
         ui: &mut Ui,
         level: &mut LevelData,
         palette: &Palette,
-    ) -> (Response, Rect, Option<Command>) {
+    ) -> (Response, Rect, Option<(Layer, Vec<BlockEdit>)>) {
+        // Reserve a shape behind the editor to draw the backdrop and the background layer.
+        let background = ui.painter().add(Shape::Noop);
+
+        self.editor.set_area_painted(self.tool == Tool::Rectangle);
         let (widget_response, widget_rect, command) = self.editor.ui(ui);
 
+        let mut edits = None;
         match command {
             Some(Command::Selection(selection, ref indexed_colors)) => self.set_selection(
                 ui.ctx(),
@@ -80,20 +226,237 @@ impl LevelEditor {
                 palette,
             ),
             Some(Command::Apply(position)) => {
-                self.apply_edit_selection(level, position, palette);
+                edits = match self.tool {
+                    Tool::Stamp => match self.bts_brush {
+                        Some(bts_tile) => Some((
+                            Layer::Layer1,
+                            self.apply_bts_brush(level, position, bts_tile),
+                        )),
+                        // Clipped to the level, as the brush may go past its edges.
+                        None => {
+                            let area =
+                                Rect::from_min_size(position, self.edit_selection.rect.size());
+                            self.rectangle_fill(level, area, palette)
+                        }
+                    },
+                    Tool::Fill => self.flood_fill(level, position, palette),
+                    Tool::Rectangle => {
+                        let area = Rect::from_min_size(position, Vec2::splat(1.0));
+                        self.rectangle_fill(level, area, palette)
+                    }
+                    Tool::Enemies | Tool::Scrolls => None,
+                };
+            }
+            Some(Command::Drag(position)) if self.tool == Tool::Stamp => {
+                self.paint_stroke(level, position, palette);
+            }
+            Some(Command::Area(area)) if self.tool == Tool::Rectangle => {
+                edits = self.rectangle_fill(level, area, palette);
+            }
+            Some(Command::Drag(_)) | Some(Command::Area(_)) | None => (),
+        }
+
+        // Finish the stroke once the button is released, even outside of the level.
+        if self.stroke.is_some() && !ui.input(|i| i.pointer.primary_down()) {
+            let stroke = self.stroke.take().unwrap();
+            if !stroke.edits.is_empty() {
+                edits = Some((stroke.layer, stroke.edits));
             }
-            None => (),
         }
 
-        if ui.input(|i| i.key_pressed(eframe::egui::Key::H)) {
+        // Letters typed in a text field, like the room filter, are not shortcuts.
+        let key_pressed =
+            |key| !ui.ctx().wants_keyboard_input() && ui.input(|i| i.key_pressed(key));
+        if key_pressed(eframe::egui::Key::H) {
             self.draw_bts = !self.draw_bts
         }
 
+        if key_pressed(eframe::egui::Key::X) {
+            self.flip_selection(ui.ctx(), true, palette);
+        }
+        if key_pressed(eframe::egui::Key::Y) {
+            self.flip_selection(ui.ctx(), false, palette);
+        }
+
+        self.draw_layers(ui, widget_rect, background, palette);
+
         if self.draw_bts {
             self.bts_layer.ui(ui, widget_rect);
         }
 
-        (widget_response, widget_rect, command)
+        if let Some(door) = self.highlighted_door {
+            self.draw_door_highlight(ui, widget_rect, level, door);
+        }
+
+        (widget_response, widget_rect, edits)
+    }
+
+    pub fn toolbar(&mut self, ui: &mut Ui, palette: &Palette) {
+        ui.horizontal(|ui| {
+            ui.selectable_value(&mut self.tool, Tool::Stamp, "Stamp")
+                .on_hover_text("Click or drag to stamp the brush");
+            ui.add_enabled(
+                self.tool == Tool::Stamp,
+                Checkbox::new(&mut self.snap_to_brush, "Snap"),
+            )
+            .on_hover_text("Align the stamps of a drag to the brush size");
+            ui.selectable_value(&mut self.tool, Tool::Fill, "Fill")
+                .on_hover_text("Fill the contiguous matching blocks with the brush");
+            ui.selectable_value(&mut self.tool, Tool::Rectangle, "Rectangle")
+                .on_hover_text("Drag a rectangle to tile the brush across it");
+            ui.selectable_value(&mut self.tool, Tool::Enemies, "Enemies")
+                .on_hover_text("Drag the enemies to move them");
+            ui.selectable_value(&mut self.tool, Tool::Scrolls, "Scrolls")
+                .on_hover_text("Click a screen to cycle its scroll: red, blue and green");
+
+            ui.separator();
+            let previous_layer = self.active_layer;
+
+            ui.selectable_value(&mut self.active_layer, Layer::Layer1, "Layer 1");
+            ui.add_enabled_ui(self.has_layer2, |ui| {
+                ui.selectable_value(&mut self.active_layer, Layer::Layer2, "Layer 2");
+            });
+
+            ui.separator();
+            ui.checkbox(&mut self.show_layer1, "Show Layer 1");
+            ui.add_enabled(
+                self.has_layer2,
+                Checkbox::new(&mut self.show_layer2, "Show Layer 2"),
+            );
+            ui.add_enabled(
+                self.tool != Tool::Enemies,
+                Checkbox::new(&mut self.show_enemies, "Show enemies"),
+            );
+            ui.checkbox(&mut self.show_plms, "Show PLMs");
+            ui.add_enabled(
+                self.tool != Tool::Scrolls,
+                Checkbox::new(&mut self.show_scrolls, "Show scrolls"),
+            );
+
+            if previous_layer != self.active_layer {
+                self.editor.swap_texture(&mut self.inactive_layer);
+            }
+            // Layer 2 is drawn with the background when edited, see `draw_layers`.
+            self.editor
+                .set_texture_visible(self.active_layer == Layer::Layer1 && self.show_layer1);
+
+            ui.separator();
+            if ui
+                .selectable_label(self.brush_flip[0], "Flip X")
+                .on_hover_text("Mirror the brush horizontally (X)")
+                .clicked()
+            {
+                self.flip_selection(ui.ctx(), true, palette);
+            }
+            if ui
+                .selectable_label(self.brush_flip[1], "Flip Y")
+                .on_hover_text("Mirror the brush vertically (Y)")
+                .clicked()
+            {
+                self.flip_selection(ui.ctx(), false, palette);
+            }
+        });
+    }
+
+    fn flip_selection(&mut self, ctx: &Context, horizontally: bool, palette: &Palette) {
+        if self.edit_selection.data.is_empty() {
+            return;
+        }
+
+        if horizontally {
+            self.edit_selection.flip_horizontally();
+            self.brush_flip[0] = !self.brush_flip[0];
+        } else {
+            self.edit_selection.flip_vertically();
+            self.brush_flip[1] = !self.brush_flip[1];
+        }
+
+        self.editor.set_selection(
+            ctx,
+            &self.edit_selection.indexed_colors,
+            self.edit_selection.rect,
+            palette,
+        );
+    }
+
+    fn draw_layers(&self, ui: &mut Ui, widget_rect: Rect, background: ShapeIdx, palette: &Palette) {
+        let backdrop: Rgb888 = palette.sub_palettes[0].colors[0].into();
+        let mut shapes = vec![Shape::rect_filled(
+            widget_rect,
+            0.0,
+            Color32::from_rgb(backdrop.r, backdrop.g, backdrop.b),
+        )];
+
+        // Layer 2 is always drawn behind Layer 1, whichever of them is being edited.
+        // When editing Layer 2, both are drawn here, behind the brush preview of the editor.
+        let (layer1, layer2) = match self.active_layer {
+            Layer::Layer1 => (None, self.inactive_layer.texture.texture.as_ref()),
+            Layer::Layer2 => (
+                self.inactive_layer.texture.texture.as_ref(),
+                self.editor.texture(),
+            ),
+        };
+        let layers = [(layer2, self.show_layer2), (layer1, self.show_layer1)];
+        for (texture, visible) in layers {
+            let Some(texture) = texture.filter(|_| visible) else {continue};
+            shapes.push(Shape::image(
+                texture.id(),
+                widget_rect,
+                Rect::from_min_max(Pos2::ZERO, Pos2::new(1.0, 1.0)),
+                Color32::WHITE,
+            ));
+        }
+
+        ui.painter().set(background, shapes);
+    }
+
+    fn draw_door_highlight(&self, ui: &mut Ui, widget_rect: Rect, level: &LevelData, door: usize) {
+        let width_in_blocks = self.editor.size().x as usize / BLOCK_SIZE;
+        if width_in_blocks == 0 {
+            return;
+        }
+        let block_size = widget_rect.width() / width_in_blocks as f32;
+
+        let painter = ui.painter_at(widget_rect);
+        let door_blocks = level.layer1.iter().zip(level.bts.iter()).enumerate();
+        for (index, (block, bts)) in door_blocks {
+            if block.block_type == BlockType::Door && *bts as usize == door {
+                let position = Vec2::new(
+                    (index % width_in_blocks) as f32,
+                    (index / width_in_blocks) as f32,
+                );
+                painter.rect_stroke(
+                    Rect::from_min_size(
+                        widget_rect.min + position * block_size,
+                        Vec2::splat(block_size),
+                    ),
+                    0.0,
+                    PaintStroke::new(2.0, Color32::YELLOW),
+                );
+            }
+        }
+    }
+
+    pub fn set_highlighted_door(&mut self, door: Option<usize>) {
+        self.highlighted_door = door;
+    }
+
+    pub fn tool(&self) -> Tool {
+        self.tool
+    }
+
+    /// Enemies are always shown while they are being edited.
+    pub fn enemies_visible(&self) -> bool {
+        self.show_enemies || self.tool == Tool::Enemies
+    }
+
+    pub fn plms_visible(&self) -> bool {
+        self.show_plms
+    }
+
+    /// Scrolls are always shown while they are being edited.
+    pub fn scrolls_visible(&self) -> bool {
+        self.show_scrolls || self.tool == Tool::Scrolls
     }
 
     fn extract_selected_tiles(&self, level: &mut LevelData, selection: Rect) -> Vec<(Block, u8)> {
@@ -103,76 +466,312 @@ impl LevelEditor {
         for x in (selection.min.x as usize)..(selection.max.x as usize) {
             for y in (selection.min.y as usize)..(selection.max.y as usize) {
                 let index = x + y * width_in_blocks;
-                selected_tiles.push((level.layer1[index], level.bts[index]));
+                selected_tiles.push(match self.active_layer {
+                    Layer::Layer1 => (level.layer1[index], level.bts[index]),
+                    // BTS only exists for Layer 1.
+                    Layer::Layer2 => (level.layer2[index], 0),
+                });
             }
         }
 
         selected_tiles
     }
 
-    pub fn apply_edit_selection(
+    /// Change the block type and BTS of the clicked block, keeping its graphic block number.
+    fn apply_bts_brush(
+        &mut self,
+        level: &mut LevelData,
+        position: Pos2,
+        bts_tile: BtsTile,
+    ) -> Vec<BlockEdit> {
+        let width_in_blocks = self.editor.size().x as usize / BLOCK_SIZE;
+        let index = (position.x as usize) + (position.y as usize) * width_in_blocks;
+
+        let old = (level.layer1[index], level.bts[index]);
+        let mut block = old.0;
+        block.block_type = bts_tile.block_type;
+
+        level.layer1[index] = block;
+        level.bts[index] = bts_tile.bts_block;
+
+        self.draw_bts_icon([position.x as usize, position.y as usize], bts_tile);
+
+        vec![BlockEdit {
+            index,
+            old,
+            new: (block, bts_tile.bts_block),
+        }]
+    }
+
+    /// Draw the icon of a block onto the bts texture, or clear it if there is no icon for it.
+    fn draw_bts_icon(&mut self, block_position: [usize; 2], bts_tile: BtsTile) {
+        let bts_icon = self
+            .bts_icons
+            .lock()
+            .unwrap()
+            .get(&bts_tile)
+            .cloned()
+            .unwrap_or_else(|| ColorImage::new([BLOCK_SIZE, BLOCK_SIZE], Color32::TRANSPARENT));
+        self.bts_layer.texture.as_mut().unwrap().set_partial(
+            [
+                block_position[0] * BLOCK_SIZE,
+                block_position[1] * BLOCK_SIZE,
+            ],
+            bts_icon,
+            TextureOptions::NEAREST,
+        );
+    }
+
+    fn has_brush(&self) -> bool {
+        self.bts_brush.is_some() || !self.edit_selection.data.is_empty()
+    }
+
+    // The BTS brush always paints on Layer 1, as BTS only exists there.
+    fn paint_layer(&self) -> Layer {
+        if self.bts_brush.is_some() {
+            Layer::Layer1
+        } else {
+            self.active_layer
+        }
+    }
+
+    /// Stamp the brush on each newly covered cell while dragging.
+    fn paint_stroke(&mut self, level: &mut LevelData, position: Pos2, palette: &Palette) {
+        if !self.has_brush() {
+            return;
+        }
+
+        let brush_size = if self.bts_brush.is_some() {
+            Vec2::splat(1.0)
+        } else {
+            self.edit_selection.rect.size()
+        };
+        let layer = self.paint_layer();
+        let stroke = self.stroke.get_or_insert(Stroke {
+            start: position,
+            last_position: position,
+            last_stamp: None,
+            layer,
+            edits: Vec::new(),
+        });
+
+        // Walk from the last position, so fast pointer moves don't leave gaps.
+        let mut stamps = Vec::new();
+        let steps = (position - stroke.last_position).abs().max_elem() as usize;
+        for step in 0..=steps {
+            let t = if steps == 0 {
+                1.0
+            } else {
+                step as f32 / steps as f32
+            };
+            let mut stamp = (stroke.last_position + (position - stroke.last_position) * t).round();
+            if self.snap_to_brush {
+                stamp = stroke.start + ((stamp - stroke.start) / brush_size).floor() * brush_size;
+            }
+
+            if stroke.last_stamp != Some(stamp) {
+                stroke.last_stamp = Some(stamp);
+                stamps.push(stamp);
+            }
+        }
+        stroke.last_position = position;
+
+        if stamps.is_empty() {
+            return;
+        }
+        for stamp in stamps {
+            let area = Rect::from_min_size(stamp, brush_size);
+            let (_, edits) = self.tile_rectangle(level, area);
+            self.stroke.as_mut().unwrap().edits.extend(edits);
+        }
+        self.upload_brush_colors(palette);
+    }
+
+    /// Fill the contiguous area of blocks matching the clicked one with the brush.
+    fn flood_fill(
         &mut self,
         level: &mut LevelData,
         position: Pos2,
         palette: &Palette,
-    ) {
+    ) -> Option<(Layer, Vec<BlockEdit>)> {
+        if !self.has_brush() {
+            return None;
+        }
+
+        let layer = self.paint_layer();
         let width_in_blocks = self.editor.size().x as usize / BLOCK_SIZE;
-        let mut selected_tiles = self.edit_selection.data.iter();
-
-        // Apply them to the level, from the extracted tiles.
-        let index_cursor_position = (position.x as usize) + (position.y as usize) * width_in_blocks;
-        for x in 0..self.edit_selection.rect.width() as usize {
-            for y in 0..self.edit_selection.rect.height() as usize {
-                let index = index_cursor_position + x + y * width_in_blocks;
-                if let Some((layer1_block, bts)) = selected_tiles.next() {
-                    level.layer1[index] = *layer1_block;
-                    level.bts[index] = *bts;
+        let height_in_blocks = level.layer1.len() / width_in_blocks;
+
+        let start = [position.x as usize, position.y as usize];
+        let target = Self::block_key(level, layer, start[0] + start[1] * width_in_blocks);
+
+        let mut visited = vec![false; level.layer1.len()];
+        visited[start[0] + start[1] * width_in_blocks] = true;
+
+        let mut cells = Vec::new();
+        let mut to_visit = vec![start];
+        while let Some([x, y]) = to_visit.pop() {
+            cells.push([x, y]);
+
+            let neighbours = [
+                (x > 0).then(|| [x - 1, y]),
+                (x + 1 < width_in_blocks).then(|| [x + 1, y]),
+                (y > 0).then(|| [x, y - 1]),
+                (y + 1 < height_in_blocks).then(|| [x, y + 1]),
+            ];
+            for [x, y] in neighbours.into_iter().flatten() {
+                let index = x + y * width_in_blocks;
+                if !visited[index] && Self::block_key(level, layer, index) == target {
+                    visited[index] = true;
+                    to_visit.push([x, y]);
                 }
             }
         }
 
-        // Draw them onto texture.
-        self.editor.edit_texture(
-            position,
-            self.edit_selection.rect.width(),
-            &self.edit_selection.indexed_colors,
-            palette,
-        );
+        let anchor = [start[0] as isize, start[1] as isize];
+        let edits = self.paint_cells(level, cells, anchor);
+        self.upload_brush_colors(palette);
+        Some(edits)
+    }
 
-        // Collect bts icons to draw.
-        let bts_icons = self.edit_selection.data.iter().map(|(block, bts_block)| {
-            self.bts_icons
-                .lock()
-                .unwrap()
-                .get(&BtsTile {
-                    block_type: block.block_type,
-                    bts_block: *bts_block,
-                })
-                .cloned()
-        });
+    /// Tile the brush across the area, starting from its top left corner.
+    fn rectangle_fill(
+        &mut self,
+        level: &mut LevelData,
+        area: Rect,
+        palette: &Palette,
+    ) -> Option<(Layer, Vec<BlockEdit>)> {
+        if !self.has_brush() {
+            return None;
+        }
 
-        // Draw them onto bts texture.
-        let selection_height_in_blocks = self.edit_selection.rect.height() as usize;
-        for (i, bts_icon) in bts_icons.enumerate() {
-            if let Some(bts_icon) = bts_icon {
-                self.bts_layer.texture.as_mut().unwrap().set_partial(
-                    [
-                        (position.x as usize + (i / selection_height_in_blocks)) * BLOCK_SIZE,
-                        (position.y as usize + (i % selection_height_in_blocks)) * BLOCK_SIZE,
-                    ],
-                    bts_icon.clone(),
-                    TextureOptions::NEAREST,
+        let edits = self.tile_rectangle(level, area);
+        self.upload_brush_colors(palette);
+        Some(edits)
+    }
+
+    /// Same as `rectangle_fill`, but without uploading the texture, to batch many areas.
+    fn tile_rectangle(&mut self, level: &mut LevelData, area: Rect) -> (Layer, Vec<BlockEdit>) {
+        let width_in_blocks = self.editor.size().x as usize / BLOCK_SIZE;
+        let height_in_blocks = level.layer1.len() / width_in_blocks;
+
+        // The area may start outside the level, keep tiling from its corner anyway.
+        let anchor = [area.min.x as isize, area.min.y as isize];
+        let min = [area.min.x.max(0.0) as usize, area.min.y.max(0.0) as usize];
+        let max = [
+            (area.max.x.max(0.0) as usize).min(width_in_blocks),
+            (area.max.y.max(0.0) as usize).min(height_in_blocks),
+        ];
+        let cells = (min[1]..max[1])
+            .flat_map(|y| (min[0]..max[0]).map(move |x| [x, y]))
+            .collect();
+
+        self.paint_cells(level, cells, anchor)
+    }
+
+    /// Paint each cell with the brush block that falls on it, when tiling the brush from `anchor`.
+    /// The texture is only written, see `upload_brush_colors`.
+    fn paint_cells(
+        &mut self,
+        level: &mut LevelData,
+        cells: Vec<[usize; 2]>,
+        anchor: [isize; 2],
+    ) -> (Layer, Vec<BlockEdit>) {
+        let layer = self.paint_layer();
+        let width_in_blocks = self.editor.size().x as usize / BLOCK_SIZE;
+        let brush_size = [
+            self.edit_selection.rect.width() as isize,
+            self.edit_selection.rect.height() as isize,
+        ];
+
+        let mut edits = Vec::with_capacity(cells.len());
+        for [x, y] in cells {
+            let index = x + y * width_in_blocks;
+            let old = match layer {
+                Layer::Layer1 => (level.layer1[index], level.bts[index]),
+                Layer::Layer2 => (level.layer2[index], level.bts[index]),
+            };
+
+            let new = if let Some(bts_tile) = self.bts_brush {
+                let mut block = old.0;
+                block.block_type = bts_tile.block_type;
+                (block, bts_tile.bts_block)
+            } else {
+                let brush_x = (x as isize - anchor[0]).rem_euclid(brush_size[0]) as usize;
+                let brush_y = (y as isize - anchor[1]).rem_euclid(brush_size[1]) as usize;
+
+                self.editor.write_texture(
+                    Pos2::new(x as f32, y as f32),
+                    1.0,
+                    &self.edit_selection.block_colors(brush_x, brush_y),
+                );
+
+                let (block, bts) = self.edit_selection.block(brush_x, brush_y);
+                match layer {
+                    Layer::Layer1 => (block, bts),
+                    Layer::Layer2 => (block, old.1),
+                }
+            };
+
+            write_block(level, layer, index, new);
+            if layer == Layer::Layer1 {
+                self.draw_bts_icon(
+                    [x, y],
+                    BtsTile {
+                        block_type: new.0.block_type,
+                        bts_block: new.1,
+                    },
                 );
             }
+
+            edits.push(BlockEdit { index, old, new });
+        }
+
+        (layer, edits)
+    }
+
+    /// Upload the texture once, after painting cells with the brush.
+    fn upload_brush_colors(&mut self, palette: &Palette) {
+        if self.bts_brush.is_none() {
+            self.editor.upload_texture(palette);
+        }
+    }
+
+    fn block_key(level: &LevelData, layer: Layer, index: usize) -> (u16, u8, bool, bool, BtsBlock) {
+        let (block, bts) = match layer {
+            Layer::Layer1 => (level.layer1[index], level.bts[index]),
+            Layer::Layer2 => (level.layer2[index], 0),
+        };
+        (
+            block.block_number,
+            block.block_type as u8,
+            block.x_flip,
+            block.y_flip,
+            bts,
+        )
+    }
+
+    pub fn bts_brush(&self) -> Option<BtsTile> {
+        self.bts_brush
+    }
+
+    pub fn set_bts_brush(&mut self, bts_tile: Option<BtsTile>) {
+        self.bts_brush = bts_tile;
+        if bts_tile.is_some() {
+            self.edit_selection = BlockSelection::default();
+            self.brush_flip = [false, false];
+            self.editor.clear_selection();
         }
     }
 
     pub fn apply_colors(&mut self, palette: &Palette) {
         self.editor.apply_colors(palette);
+        self.inactive_layer.apply_colors(palette);
     }
 
     pub fn clear_selection(&mut self) {
         self.edit_selection = BlockSelection::default();
+        self.brush_flip = [false, false];
         self.editor.clear_selection();
     }
 
@@ -181,13 +780,33 @@ impl LevelEditor {
         ctx: &Context,
         level_data: &LevelData,
         indexed_colors: Vec<zen::graphics::IndexedColor>,
+        layer2_indexed_colors: Option<Vec<IndexedColor>>,
         palette: Palette,
         texture_size: [usize; 2],
     ) {
+        // The editor always receives Layer 1, swap it back if Layer 2 was being edited.
+        if self.active_layer == Layer::Layer2 {
+            self.editor.swap_texture(&mut self.inactive_layer);
+        }
+
         self.editor.set_size(texture_size);
         self.editor
             .load_colors(ctx, indexed_colors, &palette, texture_size);
 
+        self.has_layer2 = layer2_indexed_colors.is_some();
+        if let Some(layer2_indexed_colors) = layer2_indexed_colors {
+            self.inactive_layer
+                .load_colors(ctx, layer2_indexed_colors, &palette, texture_size);
+        } else {
+            self.inactive_layer = IndexedTexture::new("InactiveLayer_LevelEditor".to_string());
+            self.inactive_layer.transparent = true;
+            self.active_layer = Layer::Layer1;
+        }
+
+        if self.active_layer == Layer::Layer2 {
+            self.editor.swap_texture(&mut self.inactive_layer);
+        }
+
         self.bts_layer.texture = Some(ctx.load_texture(
             "BTS Texture",
             ColorImage::from_rgba_unmultiplied(
@@ -197,27 +816,22 @@ impl LevelEditor {
             TextureOptions::NEAREST,
         ));
 
+        let mut missing_icons: Vec<BtsTile> = Vec::new();
         let bts_icons =
             level_data
                 .layer1
                 .iter()
                 .zip(level_data.bts.iter())
                 .map(|(block, bts_block)| {
-                    self.bts_icons
-                        .lock()
-                        .unwrap()
-                        .get(&BtsTile {
-                            block_type: block.block_type,
-                            bts_block: *bts_block,
-                        })
-                        .or_else(|| {
-                            println!(
-                                "Block/BTS not found: ({:?} - {:#x})",
-                                block.block_type, bts_block
-                            );
-                            None
-                        })
-                        .cloned()
+                    let bts_tile = BtsTile {
+                        block_type: block.block_type,
+                        bts_block: *bts_block,
+                    };
+                    let bts_icon = self.bts_icons.lock().unwrap().get(&bts_tile).cloned();
+                    if bts_icon.is_none() && self.reported_icons.insert(bts_tile) {
+                        missing_icons.push(bts_tile);
+                    }
+                    bts_icon
                 });
 
         let x_blocks = texture_size[0] / BLOCK_SIZE;
@@ -230,6 +844,19 @@ impl LevelEditor {
                 );
             }
         }
+
+        if !missing_icons.is_empty() {
+            let missing_icons: Vec<String> = missing_icons
+                .iter()
+                .map(|bts_tile| format!("({:?} - {:#x})", bts_tile.block_type, bts_tile.bts_block))
+                .collect();
+            notifications::warning(format!("Block/BTS not found: {}", missing_icons.join(", ")));
+        }
+    }
+
+    /// Report the missing icons again, for a newly loaded ROM.
+    pub fn clear_reported_icons(&mut self) {
+        self.reported_icons.clear();
     }
 
     pub fn set_selection(
@@ -245,5 +872,7 @@ impl LevelEditor {
             palette,
         );
         self.edit_selection = block_selection;
+        self.brush_flip = [false, false];
+        self.bts_brush = None;
     }
 }
@@ -1,12 +1,32 @@
+mod block_composer;
+mod bts_palette;
+mod door_list;
+mod enemy_editor;
 mod graphics;
 mod helpers;
 mod level_editor;
+mod notification_log;
 mod palette;
+mod plm_editor;
+mod room_browser;
+mod room_properties;
+mod scroll_overlay;
 mod tiletable;
 
-pub use graphics::GraphicsEditor;
+pub use block_composer::{read_block_entries, write_block_entries, BlockComposer, TileEntry};
+pub use bts_palette::BtsPalette;
+pub use door_list::{DoorList, DoorListCommand};
+pub use enemy_editor::{EnemyCommand, EnemyEditor};
+pub use graphics::{GraphicsCommand, GraphicsEditor};
+pub use level_editor::Layer;
 pub use level_editor::LevelEditor;
-pub use palette::PaletteEditor;
+pub use level_editor::Tool;
+pub use notification_log::NotificationLog;
+pub use palette::{PaletteEditor, PaletteFileCommand};
+pub use plm_editor::PlmEditor;
+pub use room_browser::{RoomBookmarks, RoomBrowser, RoomBrowserCommand};
+pub use room_properties::RoomProperties;
+pub use scroll_overlay::scroll_overlay;
 pub use tiletable::TileTableCommand;
 pub use tiletable::TileTableEditor;
 
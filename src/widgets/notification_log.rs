@@ -0,0 +1,87 @@
+use std::time::Duration;
+
+use eframe::{
+    egui::{Align2, Area, Context, Frame, Id, Order, ScrollArea, Window},
+    epaint::Color32,
+};
+
+use crate::notifications::{self, Level};
+
+const TOAST_DURATION: f64 = 5.0; // Seconds.
+const MAX_ENTRIES: usize = 500; // The oldest entries are dropped past it.
+
+struct Entry {
+    level: Level,
+    message: String,
+    time: f64, // When it was reported, in seconds since the app started.
+}
+
+/// Shows the messages reported through `notifications`, as toasts and in a log window.
+#[derive(Default)]
+pub struct NotificationLog {
+    entries: Vec<Entry>,
+    open: bool,
+}
+
+impl NotificationLog {
+    pub fn ui(&mut self, ctx: &Context) {
+        let now = ctx.input(|i| i.time);
+        for (level, message) in notifications::take() {
+            self.entries.push(Entry {
+                level,
+                message,
+                time: now,
+            });
+        }
+        let overflow = self.entries.len().saturating_sub(MAX_ENTRIES);
+        self.entries.drain(..overflow);
+
+        let toasts: Vec<&Entry> = self
+            .entries
+            .iter()
+            .filter(|entry| now - entry.time < TOAST_DURATION)
+            .collect();
+        if !toasts.is_empty() {
+            Area::new(Id::new("NotificationToasts"))
+                .anchor(Align2::RIGHT_BOTTOM, [-8.0, -8.0])
+                .order(Order::Foreground)
+                .show(ctx, |ui| {
+                    for entry in toasts.iter() {
+                        Frame::popup(ui.style()).show(ui, |ui| {
+                            ui.colored_label(level_color(entry.level), &entry.message);
+                        });
+                    }
+                });
+            // Repaint when the oldest toast expires, even without input.
+            let expiry = toasts[0].time + TOAST_DURATION - now;
+            ctx.request_repaint_after(Duration::from_secs_f64(expiry.max(0.0)));
+        }
+
+        Window::new("Error log")
+            .open(&mut self.open)
+            .default_width(400.0)
+            .show(ctx, |ui| {
+                if ui.button("Clear").clicked() {
+                    self.entries.clear();
+                }
+                ScrollArea::vertical().show(ui, |ui| {
+                    for entry in self.entries.iter().rev() {
+                        let text = format!("[{:.0}s] {}", entry.time, entry.message);
+                        ui.colored_label(level_color(entry.level), text);
+                    }
+                });
+            });
+    }
+
+    pub fn open(&mut self) {
+        self.open = true;
+    }
+}
+
+fn level_color(level: Level) -> Color32 {
+    match level {
+        Level::Info => Color32::LIGHT_GRAY,
+        Level::Warning => Color32::YELLOW,
+        Level::Error => Color32::LIGHT_RED,
+    }
+}
@@ -8,16 +8,27 @@ use zen::graphics::{
     Palette, Rgb888,
 };
 
+use crate::formats::palette::PaletteFormat;
+
 use super::helpers::{painted_selectable_area::PaintedSelectableArea, texture::Texture};
 
 const PALETTE_SIZE: [usize; 2] = [COLORS_BY_SUB_PALETTE, NUMBER_OF_SUB_PALETTES];
 const SELECTION_SIZE: [f32; 2] = [1.0, 1.0];
 
+/// Import or export colors of the whole palette, or of a single sub palette.
+pub enum PaletteFileCommand {
+    Import(Option<usize>),
+    Export(PaletteFormat, Option<usize>),
+    None,
+}
+
 pub struct PaletteEditor {
     pub texture: Texture,
     selectable_area: PaintedSelectableArea,
     color_edit_popup_id: Id, // ID for the Color Picker Popup.
     editing_color: Color32,  // Store the color being edited by the Color Picker Popup.
+    // Sub palette imported or exported from files, or the whole palette.
+    file_sub_palette: Option<usize>,
 }
 
 impl Default for PaletteEditor {
@@ -30,6 +41,7 @@ impl Default for PaletteEditor {
             ),
             color_edit_popup_id: Id::new("palette_color_popup_id"),
             editing_color: Color32::default(),
+            file_sub_palette: None,
         }
     }
 }
@@ -108,6 +120,56 @@ impl PaletteEditor {
         }
     }
 
+    pub fn file_menu(&mut self, ui: &mut Ui) -> PaletteFileCommand {
+        let mut command = PaletteFileCommand::None;
+        ui.horizontal(|ui| {
+            let text = match self.file_sub_palette {
+                Some(sub_palette) => format!("Sub palette {}", sub_palette),
+                None => "Whole palette".to_string(),
+            };
+            egui::ComboBox::from_id_source("PaletteFileScope")
+                .selected_text(text)
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.file_sub_palette, None, "Whole palette");
+                    for sub_palette in 0..NUMBER_OF_SUB_PALETTES {
+                        let text = format!("Sub palette {}", sub_palette);
+                        ui.selectable_value(&mut self.file_sub_palette, Some(sub_palette), text);
+                    }
+                });
+
+            if ui.button("Import").clicked() {
+                command = PaletteFileCommand::Import(self.file_sub_palette);
+            }
+            ui.menu_button("Export", |ui| {
+                for format in PaletteFormat::ALL {
+                    if ui.button(format.name()).clicked() {
+                        command = PaletteFileCommand::Export(format, self.file_sub_palette);
+                        ui.close_menu();
+                    }
+                }
+            });
+        });
+        command
+    }
+
+    /// Sub palette of the row clicked with the primary button.
+    pub fn clicked_sub_palette(&self, response: &Response, widget_rect: Rect) -> Option<usize> {
+        if !response.clicked() {
+            return None;
+        }
+
+        let pointer = response.interact_pointer_pos()?;
+        let row = (pointer.y - widget_rect.min.y) / widget_rect.height() * PALETTE_SIZE[1] as f32;
+        Some((row.max(0.0) as usize).min(PALETTE_SIZE[1] - 1))
+    }
+
+    /// Sub palette and color index of the color being edited.
+    pub fn selected_color(&self) -> Option<[usize; 2]> {
+        self.selectable_area
+            .position()
+            .map(|position| [position.y as usize, position.x as usize])
+    }
+
     pub fn load_texture(&mut self, ctx: &Context, colors: Vec<Rgb888>) {
         self.texture.load_colors(ctx, colors, PALETTE_SIZE);
     }
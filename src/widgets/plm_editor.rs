@@ -0,0 +1,173 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use eframe::{
+    egui::{Button, DragValue, Grid, TextureOptions, Ui},
+    epaint::{Color32, ColorImage, Pos2, Rect, Stroke, TextureHandle, Vec2},
+};
+use zen::super_metroid::tile_table::BLOCK_SIZE;
+
+use crate::{
+    history::ListEdit,
+    room::{Plm, PlmKind},
+};
+
+pub struct PlmEditor {
+    selected: Option<usize>,
+    new_id: u16, // ID of the PLMs added with nothing selected to copy.
+    plm_icons: Arc<Mutex<HashMap<PlmKind, ColorImage>>>,
+    textures: HashMap<PlmKind, TextureHandle>,
+}
+
+impl PlmEditor {
+    pub fn new(plm_icons: Arc<Mutex<HashMap<PlmKind, ColorImage>>>) -> Self {
+        Self {
+            selected: None,
+            new_id: 0,
+            plm_icons,
+            textures: HashMap::new(),
+        }
+    }
+
+    /// Draw the PLMs over the level, of `level_size` in pixels.
+    pub fn overlay(&mut self, ui: &Ui, widget_rect: Rect, level_size: Vec2, plm_set: &[Plm]) {
+        if level_size.x == 0.0 {
+            return;
+        }
+        self.load_textures(ui);
+
+        let block_size = widget_rect.width() / level_size.x * BLOCK_SIZE as f32;
+        let painter = ui.painter_at(widget_rect);
+        for (index, plm) in plm_set.iter().enumerate() {
+            let block = Rect::from_min_size(
+                widget_rect.min + Vec2::new(plm.x as f32, plm.y as f32) * block_size,
+                Vec2::splat(block_size),
+            );
+            match self.textures.get(&plm.kind()) {
+                Some(texture) => painter.image(
+                    texture.id(),
+                    block,
+                    Rect::from_min_max(Pos2::ZERO, Pos2::new(1.0, 1.0)),
+                    Color32::WHITE,
+                ),
+                // Still visible when its icon is missing.
+                None => painter.rect_stroke(block, 0.0, Stroke::new(1.0, Color32::WHITE)),
+            };
+
+            if self.selected == Some(index) {
+                painter.rect_stroke(block, 0.0, Stroke::new(2.0, Color32::YELLOW));
+            }
+        }
+    }
+
+    /// Edit the PLM set of a state. Returns the new PLM set when it changes.
+    pub fn ui(&mut self, ui: &mut Ui, plm_set: &[Plm]) -> Option<(ListEdit, Vec<Plm>)> {
+        if matches!(self.selected, Some(index) if index >= plm_set.len()) {
+            self.selected = None;
+        }
+
+        for (index, plm) in plm_set.iter().enumerate() {
+            let text = format!(
+                "{}: {:04X} {:?} ({}, {})",
+                index,
+                plm.id,
+                plm.kind(),
+                plm.x,
+                plm.y
+            );
+            if ui
+                .selectable_label(self.selected == Some(index), text)
+                .clicked()
+            {
+                self.selected = (self.selected != Some(index)).then_some(index);
+            }
+        }
+
+        let mut new = plm_set.to_vec();
+        let mut edit = None;
+        ui.horizontal(|ui| {
+            // An ID of 0 ends the PLM set, so new PLMs copy the selected one or need an ID.
+            if self.selected.is_none() {
+                ui.add(DragValue::new(&mut self.new_id).hexadecimal(4, false, true))
+                    .on_hover_text("ID of the PLM to add");
+            }
+            let can_add = self.selected.is_some() || self.new_id != 0;
+            if ui.add_enabled(can_add, Button::new("Add")).clicked() {
+                // Copy the selected PLM, next to it, as a starting point.
+                let mut plm = match self.selected {
+                    Some(index) => plm_set[index],
+                    None => Plm {
+                        id: self.new_id,
+                        ..Default::default()
+                    },
+                };
+                plm.x = plm.x.saturating_add(1);
+
+                new.push(plm);
+                self.selected = Some(new.len() - 1);
+                edit = Some(ListEdit::Added);
+            }
+            if ui
+                .add_enabled(self.selected.is_some(), Button::new("Remove"))
+                .clicked()
+            {
+                new.remove(self.selected.take().unwrap());
+                edit = Some(ListEdit::Removed);
+            }
+        });
+
+        if let Some(index) = self.selected {
+            let plm = &mut new[index];
+            let original = *plm;
+            Grid::new("PlmGrid").num_columns(2).show(ui, |ui| {
+                ui.label("ID");
+                ui.add(
+                    DragValue::new(&mut plm.id)
+                        .hexadecimal(4, false, true)
+                        .clamp_range(1..=u16::MAX),
+                );
+                ui.end_row();
+
+                ui.label("X/Y");
+                ui.horizontal(|ui| {
+                    ui.add(DragValue::new(&mut plm.x));
+                    ui.add(DragValue::new(&mut plm.y));
+                });
+                ui.end_row();
+
+                ui.label("Argument");
+                ui.add(DragValue::new(&mut plm.argument).hexadecimal(4, false, true));
+                ui.end_row();
+            });
+            if *plm != original {
+                edit = Some(ListEdit::Values(index));
+            }
+        }
+
+        edit.filter(|_| new != plm_set).map(|edit| (edit, new))
+    }
+
+    pub fn unselect(&mut self) {
+        self.selected = None;
+    }
+
+    // Icons are loaded asynchronously on web, so keep uploading the ones still missing.
+    fn load_textures(&mut self, ui: &Ui) {
+        let plm_icons = self.plm_icons.lock().unwrap();
+        if plm_icons.len() == self.textures.len() {
+            return;
+        }
+
+        for (kind, plm_icon) in plm_icons.iter() {
+            self.textures.entry(*kind).or_insert_with(|| {
+                ui.ctx().load_texture(
+                    format!("PlmIcon_{:?}", kind),
+                    plm_icon.clone(),
+                    TextureOptions::NEAREST,
+                )
+            });
+        }
+    }
+}
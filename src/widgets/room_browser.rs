@@ -0,0 +1,293 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+use eframe::{
+    egui::{Button, CollapsingHeader, Image, Key, Response, ScrollArea, TextEdit, Ui},
+    epaint::ColorImage,
+};
+use image::imageops;
+use zen::super_metroid::SuperMetroid;
+
+use super::helpers::texture::Texture;
+use crate::export::{self, RoomImageOptions};
+use crate::rom;
+use crate::room::names;
+
+const THUMBNAIL_SIZE: u32 = 256; // Longest side, in pixels.
+
+/// Room names and favorites set by the user.
+#[derive(Default)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct RoomBookmarks {
+    names: BTreeMap<usize, String>, // By room PC address, in place of the vanilla names.
+    favorites: BTreeSet<usize>,
+}
+
+impl RoomBookmarks {
+    /// Key of the bookmarks in the app storage, persisted with the `persistence` feature.
+    pub const STORAGE_KEY: &'static str = "room_bookmarks";
+}
+
+pub enum RoomBrowserCommand {
+    Select(usize), // Room address.
+    None,
+}
+
+/// Lists the rooms by area, with their names, a filter and favorites.
+pub struct RoomBrowser {
+    pub bookmarks: RoomBookmarks,
+    filter: String,
+    renaming: Option<(usize, String)>, // Room address and the name being typed.
+    thumbnail: Texture,
+    thumbnail_room: Option<usize>, // Room of the thumbnail, while one is hovered.
+}
+
+impl Default for RoomBrowser {
+    fn default() -> Self {
+        Self {
+            bookmarks: RoomBookmarks::default(),
+            filter: String::new(),
+            renaming: None,
+            thumbnail: Texture::new("RoomThumbnail".to_string()),
+            thumbnail_room: None,
+        }
+    }
+}
+
+impl RoomBrowser {
+    /// The name set by the user, else the vanilla one.
+    pub fn name(&self, room_address: usize) -> Option<&str> {
+        self.bookmarks
+            .names
+            .get(&room_address)
+            .map(String::as_str)
+            .or_else(|| names::vanilla_name(room_address))
+    }
+
+    /// SNES address of the room, followed by its name.
+    pub fn label(&self, room_address: usize) -> String {
+        let address = rom::pc_to_snes(room_address);
+        match self.name(room_address) {
+            Some(name) => format!("{:06X} {}", address, name),
+            None => format!("{:06X}", address),
+        }
+    }
+
+    /// Rooms with unsaved edits in `modified` are marked with an asterisk.
+    pub fn ui(
+        &mut self,
+        ui: &mut Ui,
+        sm: &SuperMetroid,
+        rooms: &[usize],
+        selected: Option<usize>,
+        modified: &HashSet<usize>,
+    ) -> RoomBrowserCommand {
+        ui.horizontal(|ui| {
+            ui.label("Filter");
+            ui.add(TextEdit::singleline(&mut self.filter).hint_text("Name or address"));
+            if ui.small_button("x").on_hover_text("Clear").clicked() {
+                self.filter.clear();
+            }
+        });
+        ui.separator();
+
+        let matching: Vec<usize> = rooms
+            .iter()
+            .copied()
+            .filter(|room_address| self.matches(*room_address))
+            .collect();
+        let favorites: Vec<usize> = matching
+            .iter()
+            .copied()
+            .filter(|room_address| self.bookmarks.favorites.contains(room_address))
+            .collect();
+        let mut areas: BTreeMap<u8, Vec<usize>> = BTreeMap::new();
+        for room_address in matching {
+            let Some(room) = sm.rooms.get(&room_address) else {continue};
+            areas.entry(room.area).or_default().push(room_address);
+        }
+
+        // Show every match while filtering, else let the user fold the areas.
+        let open = (!self.filter.is_empty()).then_some(true);
+        let mut selection = None;
+        let mut hovered = false;
+        ScrollArea::vertical().auto_shrink(false).show(ui, |ui| {
+            if !favorites.is_empty() {
+                CollapsingHeader::new("Favorites")
+                    .default_open(true)
+                    .open(open)
+                    .show(ui, |ui| {
+                        let (clicked, room_hovered) =
+                            self.room_list(ui, sm, &favorites, selected, modified);
+                        selection = clicked.or(selection);
+                        hovered |= room_hovered;
+                    });
+            }
+            for (area, area_rooms) in areas {
+                CollapsingHeader::new(names::area_name(area))
+                    .id_source(("RoomBrowserArea", area))
+                    .open(open)
+                    .show(ui, |ui| {
+                        let (clicked, room_hovered) =
+                            self.room_list(ui, sm, &area_rooms, selected, modified);
+                        selection = clicked.or(selection);
+                        hovered |= room_hovered;
+                    });
+            }
+        });
+
+        // Render the thumbnail again on the next hover, as the room may have been edited.
+        if !hovered {
+            self.thumbnail_room = None;
+        }
+        match selection {
+            Some(room_address) => RoomBrowserCommand::Select(room_address),
+            None => RoomBrowserCommand::None,
+        }
+    }
+
+    fn matches(&self, room_address: usize) -> bool {
+        let filter = self.filter.trim().trim_start_matches('$').to_lowercase();
+        if filter.is_empty() {
+            return true;
+        }
+
+        let address = format!("{:06x}", rom::pc_to_snes(room_address));
+        let name = self.name(room_address).unwrap_or_default().to_lowercase();
+        address.contains(&filter) || name.contains(&filter)
+    }
+
+    /// The room clicked, and whether any room is hovered.
+    fn room_list(
+        &mut self,
+        ui: &mut Ui,
+        sm: &SuperMetroid,
+        rooms: &[usize],
+        selected: Option<usize>,
+        modified: &HashSet<usize>,
+    ) -> (Option<usize>, bool) {
+        let mut clicked = None;
+        let mut hovered = false;
+        for room_address in rooms.iter().copied() {
+            let marker = if modified.contains(&room_address) {
+                " *"
+            } else {
+                ""
+            };
+            let selected = selected == Some(room_address);
+            let Some(response) = self.room_row(ui, sm, room_address, selected, marker) else {continue};
+            if response.clicked() {
+                clicked = Some(room_address);
+            }
+            hovered |= response.hovered();
+        }
+        (clicked, hovered)
+    }
+
+    /// None while the room is being renamed.
+    fn room_row(
+        &mut self,
+        ui: &mut Ui,
+        sm: &SuperMetroid,
+        room_address: usize,
+        selected: bool,
+        marker: &str,
+    ) -> Option<Response> {
+        ui.horizontal(|ui| {
+            let favorite = self.bookmarks.favorites.contains(&room_address);
+            let star = if favorite { "★" } else { "☆" };
+            if ui
+                .selectable_label(favorite, star)
+                .on_hover_text("Favorite")
+                .clicked()
+            {
+                match favorite {
+                    true => self.bookmarks.favorites.remove(&room_address),
+                    false => self.bookmarks.favorites.insert(room_address),
+                };
+            }
+
+            if let Some((renamed, name)) = self.renaming.as_mut() {
+                if *renamed == room_address {
+                    let response = ui.text_edit_singleline(name);
+                    if !response.lost_focus() {
+                        response.request_focus();
+                        return None;
+                    }
+                    // Enter and clicking away keep the name, Escape drops it.
+                    let name = name.trim().to_string();
+                    self.renaming = None;
+                    if !ui.input(|i| i.key_pressed(Key::Escape)) {
+                        self.rename(room_address, name);
+                    }
+                    return None;
+                }
+            }
+
+            let text = format!("{}{}", self.label(room_address), marker);
+            let response = ui
+                .selectable_label(selected, text)
+                .on_hover_ui(|ui| self.thumbnail_ui(ui, sm, room_address));
+            self.context_menu(&response, room_address);
+            Some(response)
+        })
+        .inner
+    }
+
+    fn context_menu(&mut self, response: &Response, room_address: usize) {
+        response.context_menu(|ui| {
+            if ui.button("Rename").clicked() {
+                let name = self.name(room_address).unwrap_or_default().to_string();
+                self.renaming = Some((room_address, name));
+                ui.close_menu();
+            }
+            let renamed = self.bookmarks.names.contains_key(&room_address);
+            if ui.add_enabled(renamed, Button::new("Reset name")).clicked() {
+                self.bookmarks.names.remove(&room_address);
+                ui.close_menu();
+            }
+        });
+    }
+
+    /// An empty name, or the vanilla one, goes back to the vanilla name.
+    fn rename(&mut self, room_address: usize, name: String) {
+        if name.is_empty() || names::vanilla_name(room_address) == Some(name.as_str()) {
+            self.bookmarks.names.remove(&room_address);
+        } else {
+            self.bookmarks.names.insert(room_address, name);
+        }
+    }
+
+    fn thumbnail_ui(&mut self, ui: &mut Ui, sm: &SuperMetroid, room_address: usize) {
+        if self.thumbnail_room != Some(room_address) {
+            self.thumbnail_room = Some(room_address);
+            self.load_thumbnail(ui, sm, room_address);
+        }
+
+        match &self.thumbnail.texture {
+            Some(texture) => ui.add(Image::new(texture).fit_to_original_size(1.0)),
+            None => ui.label("No preview"),
+        };
+    }
+
+    /// Render the first state of the room, scaled down.
+    fn load_thumbnail(&mut self, ui: &Ui, sm: &SuperMetroid, room_address: usize) {
+        self.thumbnail.texture = None;
+        self.thumbnail.image = None;
+        let Some(room) = sm.rooms.get(&room_address) else {return};
+        let Some(state_condition) = room.state_conditions.first() else {return};
+        let state_address = state_condition.state_address as usize;
+        let options = RoomImageOptions::default();
+        let bts_icons = HashMap::new(); // Unused without the BTS overlay.
+        let image = export::room_image(sm, room_address, state_address, &bts_icons, options);
+        let Some(image) = image else {return};
+
+        let scale = (THUMBNAIL_SIZE as f32 / image.width().max(image.height()) as f32).min(1.0);
+        let width = ((image.width() as f32 * scale) as u32).max(1);
+        let height = ((image.height() as f32 * scale) as u32).max(1);
+        let image = imageops::thumbnail(&image, width, height);
+        let size = [width as usize, height as usize];
+        let image = ColorImage::from_rgba_unmultiplied(size, image.as_raw());
+        self.thumbnail.load_image(ui.ctx(), image);
+    }
+}
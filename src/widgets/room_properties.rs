@@ -0,0 +1,83 @@
+use eframe::egui::{DragValue, Grid, Ui};
+
+use crate::room::RoomHeader;
+
+/// Edits a copy of the room header, which is only applied when asked to,
+/// as resizing a room reallocates its levels.
+#[derive(Default)]
+pub struct RoomProperties {
+    room_address: usize,
+    original: RoomHeader,
+    pending: RoomHeader,
+}
+
+impl RoomProperties {
+    /// Returns the new header when the user applies the changes.
+    pub fn ui(
+        &mut self,
+        ui: &mut Ui,
+        room_address: usize,
+        header: RoomHeader,
+    ) -> Option<RoomHeader> {
+        // Start over when another room is selected, or the header changed elsewhere.
+        if room_address != self.room_address || header != self.original {
+            self.room_address = room_address;
+            self.original = header;
+            self.pending = header;
+        }
+
+        Grid::new("RoomPropertiesGrid")
+            .num_columns(2)
+            .striped(true)
+            .show(ui, |ui| {
+                ui.label("Area");
+                ui.add(DragValue::new(&mut self.pending.area).clamp_range(0..=7));
+                ui.end_row();
+
+                ui.label("Map X");
+                ui.add(DragValue::new(&mut self.pending.map_x));
+                ui.end_row();
+
+                ui.label("Map Y");
+                ui.add(DragValue::new(&mut self.pending.map_y));
+                ui.end_row();
+
+                ui.label("Width (screens)");
+                ui.add(DragValue::new(&mut self.pending.width).clamp_range(1..=15));
+                ui.end_row();
+
+                ui.label("Height (screens)");
+                ui.add(DragValue::new(&mut self.pending.height).clamp_range(1..=15));
+                ui.end_row();
+
+                ui.label("Up scroller");
+                ui.add(DragValue::new(&mut self.pending.up_scroller).hexadecimal(2, false, true));
+                ui.end_row();
+
+                ui.label("Down scroller");
+                ui.add(DragValue::new(&mut self.pending.down_scroller).hexadecimal(2, false, true));
+                ui.end_row();
+
+                ui.label("Special graphics");
+                ui.add(
+                    DragValue::new(&mut self.pending.special_graphics_bits)
+                        .hexadecimal(2, false, true),
+                );
+                ui.end_row();
+            });
+
+        let mut applied = None;
+        ui.add_enabled_ui(self.pending != self.original, |ui| {
+            ui.horizontal(|ui| {
+                if ui.button("Apply").clicked() {
+                    applied = Some(self.pending);
+                }
+                if ui.button("Revert").clicked() {
+                    self.pending = self.original;
+                }
+            });
+        });
+
+        applied
+    }
+}
@@ -0,0 +1,72 @@
+use eframe::{
+    egui::{Response, Ui},
+    epaint::{Color32, Rect, Stroke, Vec2},
+};
+
+use crate::room::scroll::{self, BLUE};
+
+// Indexed by scroll value.
+const SCROLL_COLORS: [Color32; 3] = [
+    Color32::from_rgba_premultiplied(96, 0, 0, 96),
+    Color32::from_rgba_premultiplied(0, 0, 96, 96),
+    Color32::from_rgba_premultiplied(0, 96, 0, 96),
+];
+const GRID_STROKE: Stroke = Stroke {
+    width: 1.0,
+    color: Color32::WHITE,
+};
+
+/// Draw the screen grid over the level, colored by the scroll value of each screen.
+/// When `editable`, clicking a screen cycles its scroll value, returning the new scrolls.
+pub fn scroll_overlay(
+    ui: &Ui,
+    response: &Response,
+    widget_rect: Rect,
+    size_in_screens: [usize; 2],
+    scrolls: &[u8],
+    editable: bool,
+) -> Option<Vec<u8>> {
+    if size_in_screens[0] == 0 || size_in_screens[1] == 0 {
+        return None;
+    }
+    let screen_size = widget_rect.width() / size_in_screens[0] as f32;
+
+    let painter = ui.painter_at(widget_rect);
+    for y in 0..size_in_screens[1] {
+        for x in 0..size_in_screens[0] {
+            let scroll = scrolls
+                .get(x + y * size_in_screens[0])
+                .copied()
+                .unwrap_or(BLUE);
+            let screen = Rect::from_min_size(
+                widget_rect.min + Vec2::new(x as f32, y as f32) * screen_size,
+                Vec2::splat(screen_size),
+            );
+            painter.rect(
+                screen,
+                0.0,
+                SCROLL_COLORS[scroll as usize % SCROLL_COLORS.len()],
+                GRID_STROKE,
+            );
+        }
+    }
+
+    if !editable || !response.clicked() {
+        return None;
+    }
+
+    let pointer = response.interact_pointer_pos()?;
+    let screen = ((pointer - widget_rect.min) / screen_size).floor();
+    let (x, y) = (screen.x as usize, screen.y as usize);
+    if x >= size_in_screens[0] || y >= size_in_screens[1] {
+        return None;
+    }
+
+    // Scroll data shorter than the room, as found in some ROMs, gets blue screens.
+    let mut new = scrolls.to_vec();
+    new.resize(size_in_screens[0] * size_in_screens[1], BLUE);
+    let index = x + y * size_in_screens[0];
+    new[index] = scroll::next_scroll(new[index]);
+
+    Some(new)
+}